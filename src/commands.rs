@@ -3,6 +3,15 @@
 use anyhow::Result;
 use clap::{Args, Subcommand};
 
+/// Converts a group name argument to a group, an empty name means the default group
+fn group_arg<'a>(state: &crate::state::State, name: &'a str) -> Result<Option<&'a str>> {
+    if name.is_empty() {
+        return Ok(None);
+    }
+    state.workspace_name_format().validate_group_name(name)?;
+    Ok(Some(name))
+}
+
 #[derive(Args)]
 pub struct FocusGroup {
     name: String,
@@ -10,11 +19,7 @@ pub struct FocusGroup {
 
 impl FocusGroup {
     pub fn run(&self, state: crate::state::State) -> Result<()> {
-        let group = if self.name.is_empty() {
-            None
-        } else {
-            Some(self.name.as_str())
-        };
+        let group = group_arg(&state, &self.name)?;
         crate::workspace_group::focus_group(&state, group)
     }
 }
@@ -37,7 +42,52 @@ pub struct FocusI3Workspace {
 
 impl FocusI3Workspace {
     pub fn run(&self, state: crate::state::State) -> Result<()> {
-        crate::workspace_group::Workspace::of_i3_workspace_name(&state, &self.name)?.focus(&state)
+        crate::workspace_group::focus_i3_workspace(&state, &self.name)
+    }
+}
+
+#[derive(Args)]
+pub struct FocusAdjacentWorkspace {
+    /// Go from the last workspace of the group to the first one and vice versa
+    #[clap(long)]
+    wrap: bool,
+    /// Skip workspaces without windows
+    #[clap(long)]
+    skip_empty: bool,
+}
+
+impl FocusAdjacentWorkspace {
+    pub fn run(
+        &self,
+        state: crate::state::State,
+        direction: crate::workspace_group::Direction,
+    ) -> Result<()> {
+        crate::workspace_group::focus_adjacent_group_workspace(
+            &state,
+            direction,
+            self.wrap,
+            self.skip_empty,
+        )
+    }
+}
+
+#[derive(Args)]
+pub struct FocusAdjacentGroup {
+    /// Order of groups
+    #[clap(long, value_enum, default_value = "name")]
+    order: crate::workspace_group::GroupOrder,
+    /// Go from the last group to the first one and vice versa
+    #[clap(long)]
+    wrap: bool,
+}
+
+impl FocusAdjacentGroup {
+    pub fn run(
+        &self,
+        state: crate::state::State,
+        direction: crate::workspace_group::Direction,
+    ) -> Result<()> {
+        crate::workspace_group::focus_adjacent_group(&state, direction, self.order, self.wrap)
     }
 }
 
@@ -46,14 +96,35 @@ pub enum Focus {
     Group(FocusGroup),
     Workspace(FocusWorkspace),
     WmWorkspace(FocusI3Workspace),
+    /// Focus the next workspace of the current group
+    NextWorkspace(FocusAdjacentWorkspace),
+    /// Focus the previous workspace of the current group
+    PrevWorkspace(FocusAdjacentWorkspace),
+    /// Focus the next group
+    NextGroup(FocusAdjacentGroup),
+    /// Focus the previous group
+    PrevGroup(FocusAdjacentGroup),
+    /// Focus the most recently used workspace of the current group, back and forth
+    PreviousWorkspace,
+    /// Focus the most recently used group, back and forth
+    PreviousGroup,
 }
 
 impl Focus {
     pub fn run(&self, state: crate::state::State) -> Result<()> {
+        use crate::workspace_group::Direction;
         match self {
             Focus::Group(group) => group.run(state),
             Focus::Workspace(workspace) => workspace.run(state),
             Focus::WmWorkspace(workspace) => workspace.run(state),
+            Focus::NextWorkspace(cmd) => cmd.run(state, Direction::Next),
+            Focus::PrevWorkspace(cmd) => cmd.run(state, Direction::Prev),
+            Focus::NextGroup(cmd) => cmd.run(state, Direction::Next),
+            Focus::PrevGroup(cmd) => cmd.run(state, Direction::Prev),
+            Focus::PreviousWorkspace => {
+                crate::workspace_group::focus_previous_group_workspace(&state)
+            }
+            Focus::PreviousGroup => crate::workspace_group::focus_previous_group(&state),
         }
     }
 }
@@ -73,14 +144,14 @@ pub fn run(&self, state: crate::state::State) -> Result<()> {
                     .map(|v| v.group().cloned())
                     .collect::<HashSet<_>>();
                 for group in groups {
-                    println!("{}", group.unwrap_or_default())
+                    state.print(&group.unwrap_or_default())
                 }
                 Ok(())
             }
             List::WmWorkspaces => {
-                let list = crate::workspace_group::WorkspaceID::list(&state)?;
+                let list = crate::workspace_group::Workspace::list(&state)?;
                 for workspace in list {
-                    println!("{}", workspace.i3_workspace_name());
+                    state.print(workspace.name());
                 }
                 Ok(())
             }
@@ -91,18 +162,22 @@ pub fn run(&self, state: crate::state::State) -> Result<()> {
 #[derive(Args)]
 pub struct RenameGroup {
     pub name: String,
+    /// What to do with workspaces whose numbers already exist in the target group
+    #[clap(long, value_enum, default_value = "fail")]
+    pub on_conflict: crate::workspace_group::RenameConflict,
 }
 
 impl RenameGroup {
     pub fn run(&self, state: crate::state::State) -> Result<()> {
-        let new_group = if self.name.is_empty() {
-            None
-        } else {
-            Some(self.name.as_str())
-        };
+        let new_group = group_arg(&state, &self.name)?;
         let focused_workspace = crate::workspace_group::Workspace::get_focused(&state)?;
         let old_group = focused_workspace.id().group().map(|v| v.as_str());
-        crate::workspace_group::rename_group(&state, old_group, new_group)
+        let report =
+            crate::workspace_group::rename_group(&state, old_group, new_group, self.on_conflict)?;
+        for line in report {
+            state.print(&line)
+        }
+        Ok(())
     }
 }
 
@@ -141,10 +216,50 @@ pub fn run(&self, state: crate::state::State) -> Result<()> {
     }
 }
 
+#[derive(Args)]
+pub struct MoveWindowToGroup {
+    /// Target group, empty string for the default group
+    pub group: String,
+    /// Workspace number in the target group. Default is the last focused workspace of the group
+    pub workspace: Option<i64>,
+    /// Focus the target workspace
+    #[clap(long)]
+    pub follow: bool,
+}
+
+impl MoveWindowToGroup {
+    pub fn run(&self, state: crate::state::State) -> Result<()> {
+        let group = group_arg(&state, &self.group)?;
+        crate::workspace_group::move_window_to_group(&state, group, self.workspace, self.follow)
+    }
+}
+
+#[derive(Args)]
+pub struct MoveWorkspaceToGroup {
+    /// Target group, empty string for the default group
+    pub group: String,
+    /// Workspace number in the target group. Default is the current number of the workspace
+    pub workspace: Option<i64>,
+    /// If the number is taken in the target group, swap the two workspaces instead of using the next free number
+    #[clap(long)]
+    pub swap: bool,
+}
+
+impl MoveWorkspaceToGroup {
+    pub fn run(&self, state: crate::state::State) -> Result<()> {
+        let group = group_arg(&state, &self.group)?;
+        crate::workspace_group::move_workspace_to_group(&state, group, self.workspace, self.swap)
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Move {
     WindowToGroupWorkspace(MoveWindowToGroupWorkspace),
     WindowToWorkspace(MoveWindowToWorkspace),
+    /// Move the focused window to another group
+    WindowToGroup(MoveWindowToGroup),
+    /// Move the focused workspace to another group
+    WorkspaceToGroup(MoveWorkspaceToGroup),
 }
 
 impl Move {
@@ -152,6 +267,52 @@ pub fn run(&self, state: crate::state::State) -> Result<()> {
         match self {
             Move::WindowToGroupWorkspace(cmd) => cmd.run(state),
             Move::WindowToWorkspace(cmd) => cmd.run(state),
+            Move::WindowToGroup(cmd) => cmd.run(state),
+            Move::WorkspaceToGroup(cmd) => cmd.run(state),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct GroupClose {
+    /// Group to close
+    pub name: String,
+    /// Only print what would be done
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Move windows to this group instead of killing them. Empty string means the default group
+    #[clap(long)]
+    pub move_to: Option<String>,
+}
+
+impl GroupClose {
+    pub fn run(&self, state: crate::state::State) -> Result<()> {
+        if self.name.is_empty() {
+            anyhow::bail!("The default group can't be closed")
+        }
+        let move_to = match &self.move_to {
+            Some(group) => Some(group_arg(&state, group)?),
+            None => None,
+        };
+        let report =
+            crate::workspace_group::close_group(&state, &self.name, move_to, self.dry_run)?;
+        for line in report {
+            state.print(&line)
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Group {
+    /// Kill all windows of the group and forget it
+    Close(GroupClose),
+}
+
+impl Group {
+    pub fn run(&self, state: crate::state::State) -> Result<()> {
+        match self {
+            Group::Close(cmd) => cmd.run(state),
         }
     }
 }
@@ -2,7 +2,7 @@
 use serde::{Deserialize, Serialize};
 use structdoc::StructDoc;
 
-#[derive(Clone, Copy, Serialize, Deserialize, StructDoc, Default)]
+#[derive(Clone, Copy, Serialize, Deserialize, StructDoc, Default, Debug)]
 pub enum LogLevel {
     Critical,
     Error,
@@ -13,6 +13,20 @@ pub enum LogLevel {
     Trace,
 }
 
+impl LogLevel {
+    /// Logs the message with this level. slog macros need the level at compile time, hence the match
+    pub fn log(&self, message: &str) {
+        match self {
+            LogLevel::Critical => slog_scope::crit!("{}", message),
+            LogLevel::Error => slog_scope::error!("{}", message),
+            LogLevel::Warning => slog_scope::warn!("{}", message),
+            LogLevel::Info => slog_scope::info!("{}", message),
+            LogLevel::Debug => slog_scope::debug!("{}", message),
+            LogLevel::Trace => slog_scope::trace!("{}", message),
+        }
+    }
+}
+
 impl From<LogLevel> for slog::Level {
     fn from(level: LogLevel) -> Self {
         match level {
@@ -30,6 +44,12 @@ fn from(level: LogLevel) -> Self {
 pub struct Config {
     /// Max log level for syslog mode
     pub log_level: LogLevel,
+    /// Layout of workspace names
+    #[serde(default)]
+    pub workspace_name_format: crate::workspace_name::WorkspaceNameFormat,
+    /// Layout of names of workspaces of the focused group. Allows to show shorter labels for them in the bar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_group_name_format: Option<crate::workspace_name::CurrentGroupNameFormat>,
     /// Window events handlers
     #[serde(default)]
     pub window_event_handlers: Vec<crate::event_processor::config::window::WindowEventHandler>,
@@ -37,10 +57,112 @@ pub struct Config {
     #[serde(default)]
     pub workspace_event_handlers:
         Vec<crate::event_processor::config::workspace::WorkspaceEventHandler>,
+    /// Output (monitor hotplug) events handlers
+    #[serde(default)]
+    pub output_event_handlers: Vec<crate::event_processor::config::output::OutputEventHandler>,
+    /// Binding mode change events handlers
+    #[serde(default)]
+    pub mode_event_handlers: Vec<crate::event_processor::config::mode::ModeEventHandler>,
+    /// Binding events handlers
+    #[serde(default)]
+    pub binding_event_handlers: Vec<crate::event_processor::config::binding::BindingEventHandler>,
+    /// Shutdown (exit or restart of the window manager) events handlers
+    #[serde(default)]
+    pub shutdown_event_handlers:
+        Vec<crate::event_processor::config::shutdown::ShutdownEventHandler>,
+    /// Tick (`i3-msg -t send_tick`) events handlers
+    #[serde(default)]
+    pub tick_event_handlers: Vec<crate::event_processor::config::tick::TickEventHandler>,
 }
 
 impl Config {
+    /// Fails if any handler of the section has no actions
+    fn validate_handler_actions(
+        section: &str,
+        has_actions: impl Iterator<Item = bool>,
+    ) -> Result<()> {
+        for (index, has_actions) in has_actions.enumerate() {
+            if !has_actions {
+                anyhow::bail!("{section}[{index}]: neither action nor actions are set")
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if any handler of the section has both debounce and throttle set
+    fn validate_handler_rate_limits(
+        section: &str,
+        rate_limits: impl Iterator<Item = (Option<u64>, Option<u64>)>,
+    ) -> Result<()> {
+        for (index, rate_limit) in rate_limits.enumerate() {
+            if let (Some(_), Some(_)) = rate_limit {
+                anyhow::bail!(
+                    "{section}[{index}]: debounce_ms and throttle_ms can't be used together"
+                )
+            }
+        }
+        Ok(())
+    }
+
     fn validate(&self) -> Result<()> {
+        self.workspace_name_format.validate_round_trip()?;
+        if let Some(current_group_name_format) = &self.current_group_name_format {
+            current_group_name_format.validate_round_trip(&self.workspace_name_format)?;
+        }
+        Self::validate_handler_rate_limits(
+            "window_event_handlers",
+            self.window_event_handlers
+                .iter()
+                .map(|h| (h.debounce_ms, h.throttle_ms)),
+        )?;
+        Self::validate_handler_rate_limits(
+            "workspace_event_handlers",
+            self.workspace_event_handlers
+                .iter()
+                .map(|h| (h.debounce_ms, h.throttle_ms)),
+        )?;
+        Self::validate_handler_actions(
+            "window_event_handlers",
+            self.window_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
+        Self::validate_handler_actions(
+            "workspace_event_handlers",
+            self.workspace_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
+        Self::validate_handler_actions(
+            "output_event_handlers",
+            self.output_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
+        Self::validate_handler_actions(
+            "mode_event_handlers",
+            self.mode_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
+        Self::validate_handler_actions(
+            "binding_event_handlers",
+            self.binding_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
+        Self::validate_handler_actions(
+            "shutdown_event_handlers",
+            self.shutdown_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
+        Self::validate_handler_actions(
+            "tick_event_handlers",
+            self.tick_event_handlers
+                .iter()
+                .map(|h| h.action_list().next().is_some()),
+        )?;
         Ok(())
     }
 
@@ -0,0 +1,212 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+/// Name of the socket in $XDG_RUNTIME_DIR
+const SOCKET_FILE: &str = "i3im.sock";
+
+/// How long the client waits for the daemon to accept a command. A busy daemon is not waited for, the command is run
+/// directly instead
+const CLIENT_ACCEPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// How long the client waits for the daemon to run a command
+const CLIENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long the daemon waits for a client to send its request or its confirmation
+const SERVER_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Runs the command line arguments of a forwarded command
+pub type Handler = fn(crate::state::State, Vec<String>) -> Result<()>;
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    /// Config file used by the client, the daemon serves only clients with the same config
+    config_path: String,
+    /// Command line arguments, including the program name
+    args: Vec<String>,
+}
+
+/// Sent by the client after the daemon accepted the command. A client which gave up waiting closes the connection
+/// instead, then the daemon drops the command and the client runs it directly
+#[derive(Serialize, Deserialize)]
+struct Confirmation {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+enum Response {
+    /// The daemon is ready to run the command once the client confirms it
+    Accepted,
+    /// The command succeeded, lines should be printed to stdout
+    Done { output: Vec<String> },
+    /// The command failed
+    Failed { output: Vec<String>, error: String },
+    /// The daemon can't run the command, the client should run it by itself
+    Rejected { reason: String },
+}
+
+fn socket_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join(SOCKET_FILE))
+}
+
+fn read_line<T: serde::de::DeserializeOwned>(stream: &UnixStream) -> Result<T> {
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .context("Failed to read from socket")?;
+    serde_json::from_str(&line).context("Failed to parse message")
+}
+
+fn write_line<T: Serialize>(mut stream: &UnixStream, message: &T) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .context("Failed to write to socket")
+}
+
+/// Runs the command in the daemon if it is running. Returns false if the command has to be run directly
+pub fn forward(config_path: &str) -> Result<bool> {
+    let path = match socket_path() {
+        Some(path) => path,
+        None => return Ok(false),
+    };
+    let stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(e) => {
+            slog_scope::debug!("Daemon is not available at {:?}: {}", path, e);
+            return Ok(false);
+        }
+    };
+    stream.set_read_timeout(Some(CLIENT_ACCEPT_TIMEOUT))?;
+    write_line(
+        &stream,
+        &Request {
+            config_path: config_path.to_owned(),
+            args: std::env::args().collect(),
+        },
+    )?;
+    match read_line(&stream) {
+        Ok(Response::Accepted) => {}
+        Ok(Response::Rejected { reason }) => {
+            slog_scope::debug!("Daemon rejected the command: {}", reason);
+            return Ok(false);
+        }
+        Ok(_) => {
+            slog_scope::warn!("Unexpected answer of the daemon, running the command directly");
+            return Ok(false);
+        }
+        Err(e) => {
+            slog_scope::warn!(
+                "Daemon didn't accept the command, running it directly: {:?}",
+                e
+            );
+            return Ok(false);
+        }
+    }
+    if let Err(e) = write_line(&stream, &Confirmation {}) {
+        slog_scope::warn!(
+            "Failed to confirm the command, running it directly: {:?}",
+            e
+        );
+        return Ok(false);
+    }
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    match read_line(&stream).context("Daemon accepted the command, but didn't report its result")? {
+        Response::Done { output } => {
+            for line in output {
+                println!("{line}")
+            }
+            Ok(true)
+        }
+        Response::Failed { output, error } => {
+            for line in output {
+                println!("{line}")
+            }
+            Err(anyhow::anyhow!(error))
+        }
+        Response::Accepted | Response::Rejected { .. } => {
+            anyhow::bail!("Daemon accepted the command, but reported no result")
+        }
+    }
+}
+
+fn handle_request(state: &crate::state::State, handler: Handler, request: Request) -> Response {
+    slog_scope::debug!("Running forwarded command {:?}", request.args);
+    let state = state.with_captured_output();
+    match handler(state.clone(), request.args) {
+        Ok(()) => Response::Done {
+            output: state.take_output(),
+        },
+        Err(e) => Response::Failed {
+            output: state.take_output(),
+            error: format!("{e:?}"),
+        },
+    }
+}
+
+fn serve(
+    state: &crate::state::State,
+    config_path: &str,
+    handler: Handler,
+    stream: UnixStream,
+) -> Result<()> {
+    stream.set_read_timeout(Some(SERVER_READ_TIMEOUT))?;
+    let request: Request = read_line(&stream)?;
+    if request.config_path != config_path {
+        return write_line(
+            &stream,
+            &Response::Rejected {
+                reason: format!("daemon uses config {config_path:?}"),
+            },
+        );
+    }
+    let confirmation =
+        write_line(&stream, &Response::Accepted).and_then(|()| read_line::<Confirmation>(&stream));
+    if let Err(e) = confirmation {
+        slog_scope::info!(
+            "Client gave up on command {:?}, dropping it: {:#}",
+            request.args,
+            e
+        );
+        return Ok(());
+    }
+    write_line(&stream, &handle_request(state, handler, request))
+}
+
+fn bind(path: &PathBuf) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            anyhow::bail!("Another daemon is listening on {:?}", path)
+        }
+        slog_scope::info!("Removing stale socket {:?}", path);
+        std::fs::remove_file(path).context("Failed to remove stale socket")?;
+    }
+    UnixListener::bind(path).with_context(|| format!("Failed to bind socket {path:?}"))
+}
+
+/// Accepts commands forwarded by clients. Commands are run one by one in the order they arrive
+pub fn spawn_server(
+    state: crate::state::State,
+    config_path: String,
+    handler: Handler,
+) -> Result<()> {
+    let path = socket_path().context("XDG_RUNTIME_DIR is not set")?;
+    let listener = bind(&path)?;
+    crate::last_workspaces::keep_in_memory();
+    slog_scope::info!("Accepting commands on {:?}", path);
+    std::thread::Builder::new()
+        .name("daemon".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream
+                    .context("Failed to accept connection")
+                    .and_then(|stream| serve(&state, &config_path, handler, stream));
+                if let Err(e) = result {
+                    slog_scope::error!("Failed to serve client: {:?}", e);
+                }
+            }
+        })?;
+    Ok(())
+}
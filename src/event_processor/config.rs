@@ -217,16 +217,62 @@ pub mod event_action {
     use serde::{Deserialize, Serialize};
     use structdoc::StructDoc;
 
+    fn default_output_log_level() -> crate::config::LogLevel {
+        crate::config::LogLevel::Debug
+    }
+
     #[derive(Clone, Serialize, Deserialize, StructDoc)]
     pub struct ShellCommand {
         pub command: String,
         #[serde(default)]
         pub extra_env: HashMap<String, String>,
+        /// Kill the command (and all processes it started) if it is still running after this number of milliseconds.
+        /// A command followed by other actions of the handler is killed after 30 seconds by default
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub timeout_ms: Option<u64>,
+        /// Log level for the exit status and captured stdout/stderr of the command. Failed commands are always logged
+        /// with at least Warning level. Default is Debug
+        #[serde(default = "default_output_log_level")]
+        pub output_log_level: crate::config::LogLevel,
+    }
+
+    /// i3/sway command executed over the already opened IPC connection
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub struct I3Command {
+        /// Command to run. Placeholders like {container.id}, {container.class} (window events) or {old.name},
+        /// {current.name} (workspace events) are replaced with the values of the event. Available fields are the same
+        /// as in the I3IM_* environment variables of ShellCommand: id, name, node_type, border_width, layout, percent,
+        /// window_id, title, class, instance, window_role, transient_for, machine, mark, urgent, focused. Event type
+        /// is available as {event.type}. Mode events provide {mode.name}, binding events provide {binding.command},
+        /// {binding.event_state_mask}, {binding.input_code}, {binding.symbol} and {binding.input_type}, tick events
+        /// provide {tick.payload} and {tick.first}. Placeholders with arbitrary text, like {container.title}, must be
+        /// put inside double quotes: there `"` and `\` are escaped, while outside of quotes values with `;`, `,`, `"`,
+        /// `\`, `]` or whitespace make the command fail. Criteria are regexes, so use the `|regex` suffix to match a
+        /// value literally: `[class="^{container.class|regex}$"]`. Only {container.id} and {container.window_id} are
+        /// safe in criteria as is
+        pub command: String,
     }
 
     #[derive(Clone, Serialize, Deserialize, StructDoc)]
     pub enum EventAction {
         ShellCommand(ShellCommand),
+        I3Command(I3Command),
+    }
+
+    #[derive(Deserialize, Serialize, Clone, StructDoc)]
+    #[serde(transparent)]
+    pub struct EventActionWrapper(
+        #[serde(with = "serde_yaml::with::singleton_map")] pub EventAction,
+    );
+
+    /// What to do with the rest of the handler actions when one of them fails
+    #[derive(Clone, Copy, Serialize, Deserialize, StructDoc, Default, Debug, PartialEq, Eq)]
+    pub enum ActionFailurePolicy {
+        /// Skip the remaining actions
+        #[default]
+        Stop,
+        /// Run the remaining actions anyway
+        Continue,
     }
 }
 
@@ -319,6 +365,15 @@ pub enum WindowEventCondition {
         Machine(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
         /// Window mark
         Mark(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of window event conditions")]
+        AnyOf(Vec<WindowEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of window event conditions")]
+        AllOf(Vec<WindowEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Window event condition")]
+        Not(Box<WindowEventConditionWrapper>),
     }
 
     impl WindowEventCondition {
@@ -361,6 +416,9 @@ pub fn matches(&self, event: &i3ipc_jl::event::WindowEventInfo) -> bool {
                     &event.container,
                     i3ipc_jl::reply::WindowProperty::Mark,
                 )),
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
             }
         }
     }
@@ -375,8 +433,42 @@ pub struct WindowEventConditionWrapper(
     pub struct WindowEventHandler {
         #[serde(with = "serde_yaml::with::singleton_map")]
         pub condition_list: Vec<WindowEventConditionWrapper>,
-        #[serde(with = "serde_yaml::with::singleton_map")]
-        pub action: super::event_action::EventAction,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+        /// Run only for the last event of a burst: wait until no matching events come for this number of
+        /// milliseconds. Can't be combined with throttle_ms
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub debounce_ms: Option<u64>,
+        /// Run for at most one matching event per this number of milliseconds, other events are skipped. Can't be
+        /// combined with debounce_ms
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub throttle_ms: Option<u64>,
+        /// Handlers with higher priority are evaluated first. Handlers with equal priority are evaluated in the order
+        /// of the config file. Default is 0
+        #[serde(default)]
+        pub priority: i64,
+        /// If this handler matches the event, don't evaluate the handlers after it
+        #[serde(default)]
+        pub stop: bool,
+    }
+
+    impl WindowEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
     }
 }
 
@@ -427,12 +519,6 @@ fn from(event: &i3ipc_jl::event::inner::WorkspaceChange) -> Self {
         }
     }
 
-    #[derive(Deserialize, Serialize, Clone, StructDoc)]
-    #[serde(transparent)]
-    struct WorkspaceEventTypeWrapper(
-        #[serde(with = "serde_yaml::with::singleton_map")] pub WorkspaceEventType,
-    );
-
     #[derive(Clone, Serialize, Deserialize, StructDoc)]
     pub enum WorkspaceEventCondition {
         EventType(Vec<WorkspaceEventType>),
@@ -470,6 +556,15 @@ pub enum WorkspaceEventCondition {
         CurrentTransientFor(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
         CurrentMachine(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
         CurrentMark(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of workspace event conditions")]
+        AnyOf(Vec<WorkspaceEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of workspace event conditions")]
+        AllOf(Vec<WorkspaceEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Workspace event condition")]
+        Not(Box<WorkspaceEventConditionWrapper>),
     }
 
     impl WorkspaceEventCondition {
@@ -612,6 +707,9 @@ pub fn matches(&self, event: &i3ipc_jl::event::WorkspaceEventInfo) -> bool {
                     &event.current,
                     i3ipc_jl::reply::WindowProperty::Mark,
                 )),
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
             }
         }
     }
@@ -625,6 +723,510 @@ pub struct WorkspaceEventConditionWrapper(
     #[derive(Clone, Serialize, Deserialize, StructDoc)]
     pub struct WorkspaceEventHandler {
         pub condition_list: Vec<WorkspaceEventConditionWrapper>,
-        pub action: super::event_action::EventAction,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+        /// Run only for the last event of a burst: wait until no matching events come for this number of
+        /// milliseconds. Can't be combined with throttle_ms
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub debounce_ms: Option<u64>,
+        /// Run for at most one matching event per this number of milliseconds, other events are skipped. Can't be
+        /// combined with debounce_ms
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub throttle_ms: Option<u64>,
+        /// Handlers with higher priority are evaluated first. Handlers with equal priority are evaluated in the order
+        /// of the config file. Default is 0
+        #[serde(default)]
+        pub priority: i64,
+        /// If this handler matches the event, don't evaluate the handlers after it
+        #[serde(default)]
+        pub stop: bool,
+    }
+
+    impl WorkspaceEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
+    }
+}
+
+pub mod output {
+    use serde::{Deserialize, Serialize};
+    use structdoc::StructDoc;
+
+    /// Output event type
+    #[derive(Clone, Copy, Serialize, Deserialize, StructDoc, PartialEq, Eq, Debug)]
+    pub enum OutputEventType {
+        Unspecified,
+        Unknown,
+    }
+
+    impl OutputEventType {
+        pub fn matches(&self, change: &i3ipc_jl::event::inner::OutputChange) -> bool {
+            let other = Self::from(change);
+            other == *self
+        }
+    }
+
+    impl std::fmt::Display for OutputEventType {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl From<&i3ipc_jl::event::inner::OutputChange> for OutputEventType {
+        fn from(event: &i3ipc_jl::event::inner::OutputChange) -> Self {
+            use i3ipc_jl::event::inner::OutputChange;
+            match event {
+                OutputChange::Unspecified => Self::Unspecified,
+                OutputChange::Unknown => Self::Unknown,
+            }
+        }
+    }
+
+    /// Output event condition
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub enum OutputEventCondition {
+        /// Output event type
+        EventType(Vec<OutputEventType>),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of output event conditions")]
+        AnyOf(Vec<OutputEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of output event conditions")]
+        AllOf(Vec<OutputEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Output event condition")]
+        Not(Box<OutputEventConditionWrapper>),
+    }
+
+    impl OutputEventCondition {
+        pub fn matches(&self, event: &i3ipc_jl::event::OutputEventInfo) -> bool {
+            match self {
+                Self::EventType(v) => v.iter().any(|v| v.matches(&event.change)),
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Clone, StructDoc)]
+    #[serde(transparent)]
+    pub struct OutputEventConditionWrapper(
+        #[serde(with = "serde_yaml::with::singleton_map")] pub OutputEventCondition,
+    );
+
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub struct OutputEventHandler {
+        pub condition_list: Vec<OutputEventConditionWrapper>,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+    }
+
+    impl OutputEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
+    }
+}
+
+pub mod mode {
+    use super::StringMatch;
+    use serde::{Deserialize, Serialize};
+    use structdoc::StructDoc;
+
+    /// Binding mode event condition
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub enum ModeEventCondition {
+        /// Name of the new binding mode. The default mode is named "default"
+        Name(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of mode event conditions")]
+        AnyOf(Vec<ModeEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of mode event conditions")]
+        AllOf(Vec<ModeEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Mode event condition")]
+        Not(Box<ModeEventConditionWrapper>),
+    }
+
+    impl ModeEventCondition {
+        pub fn matches(&self, event: &i3ipc_jl::event::ModeEventInfo) -> bool {
+            match self {
+                Self::Name(v) => v.matches(&event.change),
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Clone, StructDoc)]
+    #[serde(transparent)]
+    pub struct ModeEventConditionWrapper(
+        #[serde(with = "serde_yaml::with::singleton_map")] pub ModeEventCondition,
+    );
+
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub struct ModeEventHandler {
+        pub condition_list: Vec<ModeEventConditionWrapper>,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+    }
+
+    impl ModeEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
+    }
+}
+
+pub mod binding {
+    use super::StringMatch;
+    use serde::{Deserialize, Serialize};
+    use structdoc::StructDoc;
+
+    /// Binding event type
+    #[derive(Clone, Copy, Serialize, Deserialize, StructDoc, PartialEq, Eq, Debug)]
+    pub enum BindingEventType {
+        Run,
+        Unknown,
+    }
+
+    impl BindingEventType {
+        pub fn matches(&self, change: &i3ipc_jl::event::inner::BindingChange) -> bool {
+            let other = Self::from(change);
+            other == *self
+        }
+    }
+
+    impl std::fmt::Display for BindingEventType {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl From<&i3ipc_jl::event::inner::BindingChange> for BindingEventType {
+        fn from(event: &i3ipc_jl::event::inner::BindingChange) -> Self {
+            use i3ipc_jl::event::inner::BindingChange;
+            match event {
+                BindingChange::Run => Self::Run,
+                BindingChange::Unknown => Self::Unknown,
+            }
+        }
+    }
+
+    /// Input device of the binding
+    #[derive(Clone, Copy, Serialize, Deserialize, StructDoc, PartialEq, Eq, Debug)]
+    pub enum BindingInputType {
+        Keyboard,
+        Mouse,
+        Unknown,
+    }
+
+    impl BindingInputType {
+        pub fn matches(&self, input_type: &i3ipc_jl::event::inner::InputType) -> bool {
+            let other = Self::from(input_type);
+            other == *self
+        }
+    }
+
+    impl std::fmt::Display for BindingInputType {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl From<&i3ipc_jl::event::inner::InputType> for BindingInputType {
+        fn from(input_type: &i3ipc_jl::event::inner::InputType) -> Self {
+            use i3ipc_jl::event::inner::InputType;
+            match input_type {
+                InputType::Keyboard => Self::Keyboard,
+                InputType::Mouse => Self::Mouse,
+                InputType::Unknown => Self::Unknown,
+            }
+        }
+    }
+
+    /// Binding event condition
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub enum BindingEventCondition {
+        /// Binding event type
+        EventType(Vec<BindingEventType>),
+        /// i3 command configured for the binding
+        Command(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Key symbol of the binding configured with bindsym
+        Symbol(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Matches if any of the modifier keys of the binding matches
+        EventStateMask(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Key code for bindcode bindings, number of clicks for mouse bindings, 0 otherwise
+        InputCode(i32),
+        /// Input device of the binding
+        InputType(BindingInputType),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of binding event conditions")]
+        AnyOf(Vec<BindingEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of binding event conditions")]
+        AllOf(Vec<BindingEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Binding event condition")]
+        Not(Box<BindingEventConditionWrapper>),
+    }
+
+    impl BindingEventCondition {
+        pub fn matches(&self, event: &i3ipc_jl::event::BindingEventInfo) -> bool {
+            match self {
+                Self::EventType(v) => v.iter().any(|v| v.matches(&event.change)),
+                Self::Command(v) => v.matches(&event.binding.command),
+                Self::Symbol(v) => v.matches_option(event.binding.symbol.as_deref()),
+                Self::EventStateMask(v) => event
+                    .binding
+                    .event_state_mask
+                    .iter()
+                    .any(|modifier| v.matches(modifier)),
+                Self::InputCode(v) => *v == event.binding.input_code,
+                Self::InputType(v) => v.matches(&event.binding.input_type),
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Clone, StructDoc)]
+    #[serde(transparent)]
+    pub struct BindingEventConditionWrapper(
+        #[serde(with = "serde_yaml::with::singleton_map")] pub BindingEventCondition,
+    );
+
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub struct BindingEventHandler {
+        pub condition_list: Vec<BindingEventConditionWrapper>,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+    }
+
+    impl BindingEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
+    }
+}
+
+pub mod shutdown {
+    use serde::{Deserialize, Serialize};
+    use structdoc::StructDoc;
+
+    /// Shutdown event type
+    #[derive(Clone, Copy, Serialize, Deserialize, StructDoc, PartialEq, Eq, Debug)]
+    pub enum ShutdownEventType {
+        Restart,
+        Exit,
+        Unknown,
+    }
+
+    impl ShutdownEventType {
+        pub fn matches(&self, change: &i3ipc_jl::event::inner::ShutdownChange) -> bool {
+            let other = Self::from(change);
+            other == *self
+        }
+    }
+
+    impl std::fmt::Display for ShutdownEventType {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl From<&i3ipc_jl::event::inner::ShutdownChange> for ShutdownEventType {
+        fn from(event: &i3ipc_jl::event::inner::ShutdownChange) -> Self {
+            use i3ipc_jl::event::inner::ShutdownChange;
+            match event {
+                ShutdownChange::Restart => Self::Restart,
+                ShutdownChange::Exit => Self::Exit,
+                ShutdownChange::Unknown => Self::Unknown,
+            }
+        }
+    }
+
+    /// Shutdown event condition
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub enum ShutdownEventCondition {
+        /// Shutdown event type
+        EventType(Vec<ShutdownEventType>),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of shutdown event conditions")]
+        AnyOf(Vec<ShutdownEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of shutdown event conditions")]
+        AllOf(Vec<ShutdownEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Shutdown event condition")]
+        Not(Box<ShutdownEventConditionWrapper>),
+    }
+
+    impl ShutdownEventCondition {
+        pub fn matches(&self, event: &i3ipc_jl::event::ShutdownEventInfo) -> bool {
+            match self {
+                Self::EventType(v) => v.iter().any(|v| v.matches(&event.change)),
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Clone, StructDoc)]
+    #[serde(transparent)]
+    pub struct ShutdownEventConditionWrapper(
+        #[serde(with = "serde_yaml::with::singleton_map")] pub ShutdownEventCondition,
+    );
+
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub struct ShutdownEventHandler {
+        pub condition_list: Vec<ShutdownEventConditionWrapper>,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+    }
+
+    impl ShutdownEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
+    }
+}
+
+pub mod tick {
+    use super::StringMatch;
+    use serde::{Deserialize, Serialize};
+    use structdoc::StructDoc;
+
+    /// Tick event condition
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub enum TickEventCondition {
+        /// Payload of the tick, as sent by `i3-msg -t send_tick <payload>`
+        Payload(#[serde(with = "serde_yaml::with::singleton_map")] StringMatch),
+        /// Whether this is the tick sent by the window manager when the listener subscribes to tick events
+        First(bool),
+        /// Matches if any of the nested conditions matches
+        #[structdoc(leaf = "List of tick event conditions")]
+        AnyOf(Vec<TickEventConditionWrapper>),
+        /// Matches if all of the nested conditions match
+        #[structdoc(leaf = "List of tick event conditions")]
+        AllOf(Vec<TickEventConditionWrapper>),
+        /// Matches if the nested condition doesn't match
+        #[structdoc(leaf = "Tick event condition")]
+        Not(Box<TickEventConditionWrapper>),
+    }
+
+    impl TickEventCondition {
+        pub fn matches(&self, event: &crate::event_processor::tick::TickEventInfo) -> bool {
+            match self {
+                Self::Payload(v) => v.matches(&event.payload),
+                Self::First(v) => event.first == *v,
+                Self::AnyOf(v) => v.iter().any(|v| v.0.matches(event)),
+                Self::AllOf(v) => v.iter().all(|v| v.0.matches(event)),
+                Self::Not(v) => !v.0.matches(event),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Clone, StructDoc)]
+    #[serde(transparent)]
+    pub struct TickEventConditionWrapper(
+        #[serde(with = "serde_yaml::with::singleton_map")] pub TickEventCondition,
+    );
+
+    #[derive(Clone, Serialize, Deserialize, StructDoc)]
+    pub struct TickEventHandler {
+        pub condition_list: Vec<TickEventConditionWrapper>,
+        /// Single action to run. Executed before the actions from the list
+        #[serde(
+            default,
+            with = "serde_yaml::with::singleton_map",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub action: Option<super::event_action::EventAction>,
+        /// Actions to run in order
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub actions: Vec<super::event_action::EventActionWrapper>,
+        /// What to do with the remaining actions when one of them fails
+        #[serde(default)]
+        pub on_failure: super::event_action::ActionFailurePolicy,
+    }
+
+    impl TickEventHandler {
+        pub fn action_list(&self) -> impl Iterator<Item = &super::event_action::EventAction> {
+            self.action
+                .iter()
+                .chain(self.actions.iter().map(|action| &action.0))
+        }
     }
 }
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Number of consecutive failures after which a handler gets disabled
+const FAILURES_BEFORE_DISABLE: u32 = 3;
+
+/// Time a handler is disabled for after reaching FAILURES_BEFORE_DISABLE, doubled with every next failure
+const DISABLE_INITIAL_DURATION: Duration = Duration::from_secs(10);
+
+/// Upper limit for the time a handler is disabled for
+const DISABLE_MAX_DURATION: Duration = Duration::from_secs(600);
+
+#[derive(Default)]
+struct FailureState {
+    consecutive_failures: u32,
+    disabled_until: Option<Instant>,
+}
+
+/// Tracks consecutive failures of event handlers and temporarily disables handlers which keep failing
+#[derive(Default)]
+pub struct HandlerFailures {
+    handlers: HashMap<String, FailureState>,
+}
+
+impl HandlerFailures {
+    pub fn is_disabled(&self, handler: &str) -> bool {
+        self.handlers
+            .get(handler)
+            .and_then(|state| state.disabled_until)
+            .map(|until| Instant::now() < until)
+            .unwrap_or_default()
+    }
+
+    pub fn record_success(&mut self, handler: &str) {
+        self.handlers.remove(handler);
+    }
+
+    /// Returns the time the handler is disabled for, if this failure disables it
+    pub fn record_failure(&mut self, handler: &str) -> Option<Duration> {
+        let state = self.handlers.entry(handler.to_owned()).or_default();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures < FAILURES_BEFORE_DISABLE {
+            return None;
+        }
+
+        let exponent = (state.consecutive_failures - FAILURES_BEFORE_DISABLE).min(16);
+        let duration = std::cmp::min(
+            DISABLE_INITIAL_DURATION * 2u32.pow(exponent),
+            DISABLE_MAX_DURATION,
+        );
+        state.disabled_until = Some(Instant::now() + duration);
+        Some(duration)
+    }
+
+    /// Forgets all failures, e.g. after the config was reloaded and handlers were renumbered
+    pub fn reset(&mut self) {
+        self.handlers.clear();
+    }
+}
@@ -1,2 +1,6 @@
 pub mod config;
+pub mod failures;
 pub mod processor;
+pub mod rate_limit;
+pub mod supervisor;
+pub mod tick;
@@ -6,198 +6,599 @@ mod event_action {
     use crate::event_processor::config::event_action;
     use anyhow::Result;
 
-    fn make_container_env_map(
-        prefix: &str,
-        container: &i3ipc_jl::reply::Node,
-    ) -> HashMap<String, String> {
-        let mut r = HashMap::new();
-
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_ID"),
-            format!("{}", container.id),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_NAME"),
-            container.name.clone().unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_NODE_TYPE"),
-            crate::event_processor::config::NodeType::from(&container.nodetype).to_string(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_BORDER_WIDTH"),
-            format!("{}", container.current_border_width),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_LAYOUT"),
-            crate::event_processor::config::NodeLayout::from(&container.layout).to_string(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_PERCENT"),
-            container
-                .percent
-                .map(|v| format!("{v}"))
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_ID"),
-            container.window.map(|v| format!("{v}")).unwrap_or_default(),
-        );
-
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_TITLE"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::Title)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_CLASS"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::Class)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_INSTANCE"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::Instance)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_ROLE"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::WindowRole)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_TRANSIENT_FOR"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::TransientFor)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_MACHINE"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::Machine)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
-        r.insert(
-            format!("I3IM_{prefix}CONTAINER_WINDOW_MARK"),
-            container
-                .window_properties
-                .as_ref()
-                .and_then(|window_properties| {
-                    window_properties
-                        .get(&i3ipc_jl::reply::WindowProperty::Mark)
-                        .cloned()
-                })
-                .unwrap_or_default(),
-        );
+    /// Container field, exported both as environment variable `I3IM_<PREFIX>CONTAINER_<ENV_NAME>` and as
+    /// I3Command placeholder `{<prefix>.<placeholder>}`
+    struct ContainerField {
+        env_name: &'static str,
+        placeholder: &'static str,
+        /// `None` means that the environment variable is not set and the placeholder is replaced with an empty string
+        value: Option<String>,
+    }
 
-        if container.urgent {
-            r.insert(format!("I3IM_{prefix}CONTAINER_URGENT"), "1".to_owned());
-        }
-        if container.focused {
-            r.insert(format!("I3IM_{prefix}CONTAINER_FOCUSED"), "1".to_owned());
+    impl ContainerField {
+        fn new(env_name: &'static str, placeholder: &'static str, value: String) -> Self {
+            Self {
+                env_name,
+                placeholder,
+                value: Some(value),
+            }
         }
+    }
 
-        r
+    fn window_property(
+        container: &i3ipc_jl::reply::Node,
+        property: i3ipc_jl::reply::WindowProperty,
+    ) -> String {
+        container
+            .window_properties
+            .as_ref()
+            .and_then(|window_properties| window_properties.get(&property).cloned())
+            .unwrap_or_default()
     }
 
-    fn make_env_map(event: &i3ipc_jl::event::Event) -> HashMap<String, String> {
-        let mut r = HashMap::new();
-        r.insert("I3IM_EVENT".to_owned(), "1".to_owned());
+    fn container_fields(container: &i3ipc_jl::reply::Node) -> Vec<ContainerField> {
+        use i3ipc_jl::reply::WindowProperty;
+
+        vec![
+            ContainerField::new("ID", "id", format!("{}", container.id)),
+            ContainerField::new("NAME", "name", container.name.clone().unwrap_or_default()),
+            ContainerField::new(
+                "NODE_TYPE",
+                "node_type",
+                crate::event_processor::config::NodeType::from(&container.nodetype).to_string(),
+            ),
+            ContainerField::new(
+                "BORDER_WIDTH",
+                "border_width",
+                format!("{}", container.current_border_width),
+            ),
+            ContainerField::new(
+                "LAYOUT",
+                "layout",
+                crate::event_processor::config::NodeLayout::from(&container.layout).to_string(),
+            ),
+            ContainerField::new(
+                "PERCENT",
+                "percent",
+                container
+                    .percent
+                    .map(|v| format!("{v}"))
+                    .unwrap_or_default(),
+            ),
+            ContainerField::new(
+                "WINDOW_ID",
+                "window_id",
+                container.window.map(|v| format!("{v}")).unwrap_or_default(),
+            ),
+            ContainerField::new(
+                "WINDOW_TITLE",
+                "title",
+                window_property(container, WindowProperty::Title),
+            ),
+            ContainerField::new(
+                "WINDOW_CLASS",
+                "class",
+                window_property(container, WindowProperty::Class),
+            ),
+            ContainerField::new(
+                "WINDOW_INSTANCE",
+                "instance",
+                window_property(container, WindowProperty::Instance),
+            ),
+            ContainerField::new(
+                "WINDOW_ROLE",
+                "window_role",
+                window_property(container, WindowProperty::WindowRole),
+            ),
+            ContainerField::new(
+                "WINDOW_TRANSIENT_FOR",
+                "transient_for",
+                window_property(container, WindowProperty::TransientFor),
+            ),
+            ContainerField::new(
+                "WINDOW_MACHINE",
+                "machine",
+                window_property(container, WindowProperty::Machine),
+            ),
+            ContainerField::new(
+                "WINDOW_MARK",
+                "mark",
+                window_property(container, WindowProperty::Mark),
+            ),
+            ContainerField {
+                env_name: "URGENT",
+                placeholder: "urgent",
+                value: container.urgent.then(|| "1".to_owned()),
+            },
+            ContainerField {
+                env_name: "FOCUSED",
+                placeholder: "focused",
+                value: container.focused.then(|| "1".to_owned()),
+            },
+        ]
+    }
 
+    /// Returns containers of the event together with their environment prefix and placeholder namespace
+    fn event_containers(
+        event: &i3ipc_jl::event::Event,
+    ) -> Vec<(&'static str, &'static str, &i3ipc_jl::reply::Node)> {
         use i3ipc_jl::event::Event;
+        let mut r = Vec::new();
         match event {
-            Event::WindowEvent(e) => {
-                use crate::event_processor::config::window;
-                r.insert(
-                    "I3IM_WINDOW_EVENT_TYPE".to_owned(),
-                    window::WindowEventType::from(&e.change).to_string(),
-                );
-                r.extend(make_container_env_map("", &e.container))
-            }
+            Event::WindowEvent(e) => r.push(("", "container", &e.container)),
             Event::WorkspaceEvent(e) => {
-                use crate::event_processor::config::workspace;
-                r.insert(
-                    "I3IM_WORKSPACE_EVENT_TYPE".to_owned(),
-                    workspace::WorkspaceEventType::from(&e.change).to_string(),
-                );
                 if let Some(old_container) = &e.old {
-                    r.extend(make_container_env_map("OLD_", old_container))
+                    r.push(("OLD_", "old", old_container))
                 }
                 if let Some(current_container) = &e.current {
-                    r.extend(make_container_env_map("CURRENT_", current_container))
+                    r.push(("CURRENT_", "current", current_container))
                 }
             }
+            _ => (),
+        }
+        r
+    }
+
+    /// Returns event fields which don't belong to a container: (environment variable name without `I3IM_` prefix,
+    /// placeholder, value)
+    fn event_fields(event: &i3ipc_jl::event::Event) -> Vec<(&'static str, &'static str, String)> {
+        use crate::event_processor::config::{binding, output, shutdown, window, workspace};
+        use i3ipc_jl::event::Event;
+        match event {
+            Event::WindowEvent(e) => vec![(
+                "WINDOW_EVENT_TYPE",
+                "event.type",
+                window::WindowEventType::from(&e.change).to_string(),
+            )],
+            Event::WorkspaceEvent(e) => vec![(
+                "WORKSPACE_EVENT_TYPE",
+                "event.type",
+                workspace::WorkspaceEventType::from(&e.change).to_string(),
+            )],
+            Event::OutputEvent(e) => vec![(
+                "OUTPUT_EVENT_TYPE",
+                "event.type",
+                output::OutputEventType::from(&e.change).to_string(),
+            )],
+            Event::ModeEvent(e) => vec![("MODE_NAME", "mode.name", e.change.clone())],
+            Event::BindingEvent(e) => vec![
+                (
+                    "BINDING_EVENT_TYPE",
+                    "event.type",
+                    binding::BindingEventType::from(&e.change).to_string(),
+                ),
+                (
+                    "BINDING_COMMAND",
+                    "binding.command",
+                    e.binding.command.clone(),
+                ),
+                (
+                    "BINDING_EVENT_STATE_MASK",
+                    "binding.event_state_mask",
+                    e.binding.event_state_mask.join("+"),
+                ),
+                (
+                    "BINDING_INPUT_CODE",
+                    "binding.input_code",
+                    format!("{}", e.binding.input_code),
+                ),
+                (
+                    "BINDING_SYMBOL",
+                    "binding.symbol",
+                    e.binding.symbol.clone().unwrap_or_default(),
+                ),
+                (
+                    "BINDING_INPUT_TYPE",
+                    "binding.input_type",
+                    binding::BindingInputType::from(&e.binding.input_type).to_string(),
+                ),
+            ],
+            Event::ShutdownEvent(e) => vec![(
+                "SHUTDOWN_EVENT_TYPE",
+                "event.type",
+                shutdown::ShutdownEventType::from(&e.change).to_string(),
+            )],
             other => {
                 slog_scope::warn!("Got unexpected event: {:?}", other);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns fields of the tick event, in the same form as event_fields()
+    fn tick_fields(
+        event: &crate::event_processor::tick::TickEventInfo,
+    ) -> Vec<(&'static str, &'static str, String)> {
+        vec![
+            ("TICK_PAYLOAD", "tick.payload", event.payload.clone()),
+            (
+                "TICK_FIRST",
+                "tick.first",
+                if event.first { "1" } else { "0" }.to_owned(),
+            ),
+        ]
+    }
+
+    fn make_env_map(
+        fields: &[(&'static str, &'static str, String)],
+        containers: &[(&'static str, &'static str, &i3ipc_jl::reply::Node)],
+    ) -> HashMap<String, String> {
+        let mut r = HashMap::new();
+        r.insert("I3IM_EVENT".to_owned(), "1".to_owned());
+
+        for (env_name, _, value) in fields {
+            r.insert(format!("I3IM_{env_name}"), value.clone());
+        }
+        for (prefix, _, container) in containers {
+            for field in container_fields(container) {
+                if let Some(value) = field.value {
+                    r.insert(format!("I3IM_{prefix}CONTAINER_{}", field.env_name), value);
+                }
+            }
+        }
+
+        r
+    }
+
+    fn make_placeholder_map(
+        fields: &[(&'static str, &'static str, String)],
+        containers: &[(&'static str, &'static str, &i3ipc_jl::reply::Node)],
+    ) -> HashMap<String, String> {
+        let mut r = HashMap::new();
+
+        for (_, placeholder, value) in fields {
+            r.insert((*placeholder).to_owned(), value.clone());
+        }
+        for (_, namespace, container) in containers {
+            for field in container_fields(container) {
+                r.insert(
+                    format!("{namespace}.{}", field.placeholder),
+                    field.value.unwrap_or_default(),
+                );
             }
         }
 
         r
     }
 
+    /// Event data needed by actions. Unlike the event itself, it can be kept for delayed (debounced) execution
+    #[derive(Clone)]
+    pub struct EventContext {
+        env: HashMap<String, String>,
+        placeholders: HashMap<String, String>,
+    }
+
+    impl EventContext {
+        pub fn new(event: &i3ipc_jl::event::Event) -> Self {
+            let fields = event_fields(event);
+            let containers = event_containers(event);
+            Self {
+                env: make_env_map(&fields, &containers),
+                placeholders: make_placeholder_map(&fields, &containers),
+            }
+        }
+
+        pub fn for_tick(event: &crate::event_processor::tick::TickEventInfo) -> Self {
+            let fields = tick_fields(event);
+            Self {
+                env: make_env_map(&fields, &[]),
+                placeholders: make_placeholder_map(&fields, &[]),
+            }
+        }
+    }
+
+    /// Characters which would let a value outside of quotes end the i3 command, start a new one or end criteria.
+    /// Whitespace is rejected as well
+    const UNQUOTED_FORBIDDEN: [char; 5] = [';', ',', '"', '\\', ']'];
+
+    /// Suffix of placeholders whose values are escaped to be matched literally by criteria, which are regexes
+    const REGEX_SUFFIX: &str = "|regex";
+
+    /// Returns the value of the placeholder as it can be put into an i3 command. Inside quotes `"` and `\` are escaped.
+    /// Outside of quotes values which could end the command or criteria are rejected, as well as control characters
+    /// anywhere.
+    fn quote_placeholder_value(name: &str, value: &str, quoted: bool) -> Result<String> {
+        if value.chars().any(char::is_control) {
+            anyhow::bail!(
+                "Value of placeholder {:?} contains control characters",
+                name
+            )
+        }
+        if quoted {
+            return Ok(value.replace('\\', "\\\\").replace('"', "\\\""));
+        }
+        if value.contains(&UNQUOTED_FORBIDDEN[..]) || value.contains(char::is_whitespace) {
+            anyhow::bail!(
+                "Value {:?} of placeholder {:?} can't be used outside of quotes",
+                value,
+                name
+            )
+        }
+        Ok(value.to_owned())
+    }
+
+    /// Returns true if the text, which starts inside or outside of a quoted string, ends inside of it
+    fn ends_quoted(text: &str, mut quoted: bool) -> bool {
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if quoted => {
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                _ => {}
+            }
+        }
+        quoted
+    }
+
+    /// Replaces `{namespace.field}` placeholders with the values from the event. Unknown placeholders are kept as is.
+    /// Placeholders with arbitrary text, like window titles, must be put inside double quotes. With the `|regex` suffix
+    /// regex special characters of the value are escaped, for use in criteria like `[class="^{container.class|regex}$"]`.
+    pub(super) fn expand_placeholders(
+        template: &str,
+        placeholders: &HashMap<String, String>,
+    ) -> Result<String> {
+        let mut r = String::with_capacity(template.len());
+        let mut rest = template;
+        let mut quoted = false;
+        while let Some(start) = rest.find('{') {
+            r.push_str(&rest[..start]);
+            quoted = ends_quoted(&rest[..start], quoted);
+            let tail = &rest[start..];
+            match tail.find('}') {
+                Some(end) => {
+                    let name = &tail[1..end];
+                    let (field, as_regex) = match name.strip_suffix(REGEX_SUFFIX) {
+                        Some(field) => (field, true),
+                        None => (name, false),
+                    };
+                    match placeholders.get(field) {
+                        Some(value) => {
+                            let value = if as_regex {
+                                regex::escape(value)
+                            } else {
+                                value.clone()
+                            };
+                            r.push_str(&quote_placeholder_value(name, &value, quoted)?)
+                        }
+                        None => {
+                            slog_scope::warn!("Unknown placeholder {:?} in i3 command", name);
+                            r.push_str(&tail[..=end]);
+                            quoted = ends_quoted(&tail[..=end], quoted);
+                        }
+                    }
+                    rest = &tail[end + 1..];
+                }
+                None => {
+                    r.push_str(tail);
+                    rest = "";
+                }
+            }
+        }
+        r.push_str(rest);
+        Ok(r)
+    }
+
+    /// Timeout of a waited shell command without timeout_ms, so a hung command doesn't stop event processing for good
+    const DEFAULT_WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+    /// How a shell command action is run
+    pub enum ShellMode {
+        /// Wait for the command, its non-zero exit status is an error of the action
+        Wait,
+        /// Run the command in background. Only spawn errors are errors of the action, the result of the command is
+        /// passed to the callback when it exits
+        Background(Box<dyn FnOnce(Result<()>) + Send>),
+    }
+
     pub fn run_action(
-        event: &i3ipc_jl::event::Event,
+        state: &crate::state::State,
+        context: &EventContext,
         action: &event_action::EventAction,
+        shell_mode: ShellMode,
     ) -> Result<()> {
         match action {
             event_action::EventAction::ShellCommand(command) => {
                 slog_scope::debug!("Running shell command: {:?}", command.command);
-                std::process::Command::new("sh")
+                let mut process = std::process::Command::new("sh");
+                process
                     .envs(&command.extra_env)
-                    .envs(make_env_map(event))
+                    .envs(&context.env)
                     .arg("-c")
-                    .arg(&command.command)
-                    .stdout(std::process::Stdio::null())
-                    .stderr(std::process::Stdio::null())
-                    .spawn()?;
+                    .arg(&command.command);
+                let timeout = command.timeout_ms.map(std::time::Duration::from_millis);
+                match shell_mode {
+                    ShellMode::Wait => crate::event_processor::supervisor::run(
+                        process,
+                        &command.command,
+                        timeout.or(Some(DEFAULT_WAIT_TIMEOUT)),
+                        command.output_log_level,
+                    )?,
+                    ShellMode::Background(on_exit) => crate::event_processor::supervisor::spawn(
+                        process,
+                        &command.command,
+                        timeout,
+                        command.output_log_level,
+                        on_exit,
+                    )?,
+                }
+            }
+            event_action::EventAction::I3Command(command) => {
+                let command = expand_placeholders(&command.command, &context.placeholders)?;
+                state.run_i3_command(&command)?;
             }
         }
 
         Ok(())
     }
+
+    /// Runs actions in order. Depending on the policy, the first failure either stops the list or is logged and the
+    /// remaining actions are executed anyway. In the latter case the first error is returned after the whole list.
+    /// A shell command followed by other actions is waited for, so the next action starts after it exits and a non-zero
+    /// exit status counts as a failure. The last shell command of the list runs in background: then None is returned
+    /// and the result of the handler is recorded when the command exits.
+    pub fn run_action_list<'a>(
+        state: &crate::state::State,
+        context: &EventContext,
+        handler_id: &str,
+        actions: impl Iterator<Item = &'a event_action::EventAction>,
+        policy: event_action::ActionFailurePolicy,
+    ) -> Option<Result<()>> {
+        let actions = actions.collect::<Vec<_>>();
+        let mut first_error = None;
+        let mut background = false;
+        for (index, action) in actions.iter().enumerate() {
+            let shell_mode = if index + 1 < actions.len() {
+                ShellMode::Wait
+            } else {
+                background = matches!(action, event_action::EventAction::ShellCommand(_));
+                let state = state.clone();
+                let handler_id = handler_id.to_owned();
+                // An earlier failure of the list is recorded right away
+                let failed = first_error.is_some();
+                ShellMode::Background(Box::new(move |result| {
+                    if !failed {
+                        record_result(&state, &handler_id, result)
+                    }
+                }))
+            };
+            if let Err(err) = run_action(state, context, action, shell_mode) {
+                background = false;
+                match policy {
+                    event_action::ActionFailurePolicy::Stop => {
+                        slog_scope::error!(
+                            "Action #{} failed, skipping remaining actions (policy {:?}): {:?}",
+                            index,
+                            policy,
+                            err
+                        );
+                        return Some(Err(err));
+                    }
+                    event_action::ActionFailurePolicy::Continue => {
+                        slog_scope::error!(
+                            "Action #{} failed, continuing with remaining actions (policy {:?}): {:?}",
+                            index,
+                            policy,
+                            err
+                        );
+                        first_error.get_or_insert(err);
+                    }
+                }
+            }
+        }
+
+        match first_error {
+            Some(err) => Some(Err(err)),
+            None if background => None,
+            None => Some(Ok(())),
+        }
+    }
+
+    fn wait_debounced(state: crate::state::State, handler_id: String) {
+        use crate::event_processor::rate_limit::Pending;
+        loop {
+            match state.with_rate_limiter(|limiter| limiter.take_due(&handler_id)) {
+                Pending::Due(job) => return job(),
+                Pending::Wait(delay) => std::thread::sleep(delay),
+                Pending::Empty => return,
+            }
+        }
+    }
+
+    /// Runs the handler with respect to its debounce or throttle settings. Debounced handlers run in a separate thread
+    /// after the burst of events is over, with the context of the last event.
+    pub fn run_rate_limited_handler<'a>(
+        state: &crate::state::State,
+        context: &EventContext,
+        handler_id: &str,
+        rate_limit: crate::event_processor::rate_limit::RateLimit,
+        actions: impl Iterator<Item = &'a event_action::EventAction>,
+        policy: event_action::ActionFailurePolicy,
+    ) {
+        use crate::event_processor::rate_limit::RateLimit;
+        match rate_limit {
+            RateLimit::Unlimited => run_handler(state, context, handler_id, actions, policy),
+            RateLimit::Throttle(interval) => {
+                if state.with_rate_limiter(|limiter| limiter.throttle(handler_id, interval)) {
+                    run_handler(state, context, handler_id, actions, policy)
+                } else {
+                    slog_scope::debug!("Handler {} is throttled, skipping event", handler_id);
+                }
+            }
+            RateLimit::Debounce(delay) => {
+                let job = {
+                    let state = state.clone();
+                    let context = context.clone();
+                    let handler_id = handler_id.to_owned();
+                    let actions = actions.cloned().collect::<Vec<_>>();
+                    Box::new(move || {
+                        run_handler(&state, &context, &handler_id, actions.iter(), policy)
+                    })
+                };
+                if !state.with_rate_limiter(|limiter| limiter.debounce(handler_id, delay, job)) {
+                    slog_scope::debug!("Handler {} is debounced, postponing", handler_id);
+                    return;
+                }
+                let waiter_state = state.clone();
+                let waiter_handler_id = handler_id.to_owned();
+                let spawned = std::thread::Builder::new()
+                    .name(format!("debounce-{handler_id}"))
+                    .spawn(move || wait_debounced(waiter_state, waiter_handler_id));
+                if let Err(err) = spawned {
+                    slog_scope::error!(
+                        "Failed to start debounce thread for handler {}: {}",
+                        handler_id,
+                        err
+                    );
+                    state.with_rate_limiter(|limiter| limiter.cancel(handler_id));
+                }
+            }
+        }
+    }
+
+    /// Runs actions of the handler. Handler errors are logged and don't affect other handlers. A handler which keeps
+    /// failing is temporarily disabled.
+    pub fn run_handler<'a>(
+        state: &crate::state::State,
+        context: &EventContext,
+        handler_id: &str,
+        actions: impl Iterator<Item = &'a event_action::EventAction>,
+        policy: event_action::ActionFailurePolicy,
+    ) {
+        if state.with_handler_failures(|failures| failures.is_disabled(handler_id)) {
+            slog_scope::debug!("Handler {} is temporarily disabled, skipping", handler_id);
+            return;
+        }
+
+        if let Some(result) = run_action_list(state, context, handler_id, actions, policy) {
+            record_result(state, handler_id, result)
+        }
+    }
+
+    /// Records the result of the handler. A handler which keeps failing is temporarily disabled
+    fn record_result(state: &crate::state::State, handler_id: &str, result: Result<()>) {
+        match result {
+            Ok(()) => state.with_handler_failures(|failures| failures.record_success(handler_id)),
+            Err(err) => {
+                slog_scope::error!("Handler {} failed: {:?}", handler_id, err);
+                let disabled_for =
+                    state.with_handler_failures(|failures| failures.record_failure(handler_id));
+                if let Some(disabled_for) = disabled_for {
+                    slog_scope::warn!(
+                        "Handler {} keeps failing, disabled for {:?}",
+                        handler_id,
+                        disabled_for
+                    );
+                }
+            }
+        }
+    }
 }
 
 mod window_handler {
     use crate::event_processor::config::window::WindowEventConditionWrapper;
+    use crate::event_processor::rate_limit::RateLimit;
     use anyhow::Result;
 
     fn check_condition_list(
@@ -220,9 +621,36 @@ pub fn handle_event(
         window_event: &i3ipc_jl::event::WindowEventInfo,
     ) -> Result<()> {
         state.with_config(|config| {
-            for handler in &config.window_event_handlers {
-                if check_condition_list(&handler.condition_list, window_event) {
-                    super::event_action::run_action(event, &handler.action)?
+            // Stable sort: handlers with equal priority keep the order of the config file
+            let mut handlers = config
+                .window_event_handlers
+                .iter()
+                .enumerate()
+                .collect::<Vec<_>>();
+            handlers.sort_by_key(|(_, handler)| std::cmp::Reverse(handler.priority));
+
+            let mut context = None;
+            for (index, handler) in handlers {
+                if !check_condition_list(&handler.condition_list, window_event) {
+                    continue;
+                }
+                let handler_id = format!("window_event_handlers[{index}]");
+                let context =
+                    context.get_or_insert_with(|| super::event_action::EventContext::new(event));
+                super::event_action::run_rate_limited_handler(
+                    state,
+                    context,
+                    &handler_id,
+                    RateLimit::new(handler.debounce_ms, handler.throttle_ms),
+                    handler.action_list(),
+                    handler.on_failure,
+                );
+                if handler.stop {
+                    slog_scope::debug!(
+                        "Handler {} has stop flag, skipping remaining handlers",
+                        handler_id
+                    );
+                    break;
                 }
             }
             Ok(())
@@ -232,6 +660,7 @@ pub fn handle_event(
 
 mod workspace_handler {
     use crate::event_processor::config::workspace::WorkspaceEventConditionWrapper;
+    use crate::event_processor::rate_limit::RateLimit;
     use anyhow::Result;
 
     fn check_condition_list(
@@ -253,9 +682,74 @@ pub fn handle_event(
         workspace_event: &i3ipc_jl::event::WorkspaceEventInfo,
     ) -> Result<()> {
         state.with_config(|config| {
-            for handler in &config.workspace_event_handlers {
-                if check_condition_list(&handler.condition_list, workspace_event) {
-                    super::event_action::run_action(event, &handler.action)?
+            // Stable sort: handlers with equal priority keep the order of the config file
+            let mut handlers = config
+                .workspace_event_handlers
+                .iter()
+                .enumerate()
+                .collect::<Vec<_>>();
+            handlers.sort_by_key(|(_, handler)| std::cmp::Reverse(handler.priority));
+
+            let mut context = None;
+            for (index, handler) in handlers {
+                if !check_condition_list(&handler.condition_list, workspace_event) {
+                    continue;
+                }
+                let handler_id = format!("workspace_event_handlers[{index}]");
+                let context =
+                    context.get_or_insert_with(|| super::event_action::EventContext::new(event));
+                super::event_action::run_rate_limited_handler(
+                    state,
+                    context,
+                    &handler_id,
+                    RateLimit::new(handler.debounce_ms, handler.throttle_ms),
+                    handler.action_list(),
+                    handler.on_failure,
+                );
+                if handler.stop {
+                    slog_scope::debug!(
+                        "Handler {} has stop flag, skipping remaining handlers",
+                        handler_id
+                    );
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+mod output_handler {
+    use crate::event_processor::config::output::OutputEventConditionWrapper;
+    use anyhow::Result;
+
+    fn check_condition_list(
+        condition_list: &[OutputEventConditionWrapper],
+        event: &i3ipc_jl::event::OutputEventInfo,
+    ) -> bool {
+        condition_list
+            .iter()
+            .all(|condition| condition.0.matches(event))
+    }
+
+    pub fn handle_event(
+        state: &crate::state::State,
+        event: &i3ipc_jl::event::Event,
+        output_event: &i3ipc_jl::event::OutputEventInfo,
+    ) -> Result<()> {
+        state.with_config(|config| {
+            let mut context = None;
+            for (index, handler) in config.output_event_handlers.iter().enumerate() {
+                if check_condition_list(&handler.condition_list, output_event) {
+                    let context = context
+                        .get_or_insert_with(|| super::event_action::EventContext::new(event));
+                    super::event_action::run_handler(
+                        state,
+                        context,
+                        &format!("output_event_handlers[{index}]"),
+                        handler.action_list(),
+                        handler.on_failure,
+                    )
                 }
             }
             Ok(())
@@ -263,6 +757,163 @@ pub fn handle_event(
     }
 }
 
+mod mode_handler {
+    use crate::event_processor::config::mode::ModeEventConditionWrapper;
+    use anyhow::Result;
+
+    fn check_condition_list(
+        condition_list: &[ModeEventConditionWrapper],
+        event: &i3ipc_jl::event::ModeEventInfo,
+    ) -> bool {
+        condition_list
+            .iter()
+            .all(|condition| condition.0.matches(event))
+    }
+
+    pub fn handle_event(
+        state: &crate::state::State,
+        event: &i3ipc_jl::event::Event,
+        mode_event: &i3ipc_jl::event::ModeEventInfo,
+    ) -> Result<()> {
+        state.with_config(|config| {
+            let mut context = None;
+            for (index, handler) in config.mode_event_handlers.iter().enumerate() {
+                if check_condition_list(&handler.condition_list, mode_event) {
+                    let context = context
+                        .get_or_insert_with(|| super::event_action::EventContext::new(event));
+                    super::event_action::run_handler(
+                        state,
+                        context,
+                        &format!("mode_event_handlers[{index}]"),
+                        handler.action_list(),
+                        handler.on_failure,
+                    )
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+mod binding_handler {
+    use crate::event_processor::config::binding::BindingEventConditionWrapper;
+    use anyhow::Result;
+
+    fn check_condition_list(
+        condition_list: &[BindingEventConditionWrapper],
+        event: &i3ipc_jl::event::BindingEventInfo,
+    ) -> bool {
+        condition_list
+            .iter()
+            .all(|condition| condition.0.matches(event))
+    }
+
+    pub fn handle_event(
+        state: &crate::state::State,
+        event: &i3ipc_jl::event::Event,
+        binding_event: &i3ipc_jl::event::BindingEventInfo,
+    ) -> Result<()> {
+        state.with_config(|config| {
+            let mut context = None;
+            for (index, handler) in config.binding_event_handlers.iter().enumerate() {
+                if check_condition_list(&handler.condition_list, binding_event) {
+                    let context = context
+                        .get_or_insert_with(|| super::event_action::EventContext::new(event));
+                    super::event_action::run_handler(
+                        state,
+                        context,
+                        &format!("binding_event_handlers[{index}]"),
+                        handler.action_list(),
+                        handler.on_failure,
+                    )
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+mod shutdown_handler {
+    use crate::event_processor::config::shutdown::ShutdownEventConditionWrapper;
+    use anyhow::Result;
+
+    fn check_condition_list(
+        condition_list: &[ShutdownEventConditionWrapper],
+        event: &i3ipc_jl::event::ShutdownEventInfo,
+    ) -> bool {
+        condition_list
+            .iter()
+            .all(|condition| condition.0.matches(event))
+    }
+
+    pub fn handle_event(
+        state: &crate::state::State,
+        event: &i3ipc_jl::event::Event,
+        shutdown_event: &i3ipc_jl::event::ShutdownEventInfo,
+    ) -> Result<()> {
+        state.with_config(|config| {
+            let mut context = None;
+            for (index, handler) in config.shutdown_event_handlers.iter().enumerate() {
+                if check_condition_list(&handler.condition_list, shutdown_event) {
+                    let context = context
+                        .get_or_insert_with(|| super::event_action::EventContext::new(event));
+                    super::event_action::run_handler(
+                        state,
+                        context,
+                        &format!("shutdown_event_handlers[{index}]"),
+                        handler.action_list(),
+                        handler.on_failure,
+                    )
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+mod tick_handler {
+    use crate::event_processor::config::tick::TickEventConditionWrapper;
+    use crate::event_processor::tick::TickEventInfo;
+
+    fn check_condition_list(
+        condition_list: &[TickEventConditionWrapper],
+        event: &TickEventInfo,
+    ) -> bool {
+        condition_list
+            .iter()
+            .all(|condition| condition.0.matches(event))
+    }
+
+    pub fn handle_event(state: &crate::state::State, tick_event: &TickEventInfo) {
+        state.with_config(|config| {
+            let mut context = None;
+            for (index, handler) in config.tick_event_handlers.iter().enumerate() {
+                if check_condition_list(&handler.condition_list, tick_event) {
+                    let context = context.get_or_insert_with(|| {
+                        super::event_action::EventContext::for_tick(tick_event)
+                    });
+                    super::event_action::run_handler(
+                        state,
+                        context,
+                        &format!("tick_event_handlers[{index}]"),
+                        handler.action_list(),
+                        handler.on_failure,
+                    )
+                }
+            }
+        })
+    }
+}
+
+/// Handles tick events, which are read over a separate connection
+pub fn handle_tick_event(
+    state: &crate::state::State,
+    event: &crate::event_processor::tick::TickEventInfo,
+) {
+    slog_scope::debug!("Tick event: {:?}", event);
+    tick_handler::handle_event(state, event)
+}
+
 pub fn handle_event(state: &crate::state::State, event: &i3ipc_jl::event::Event) -> Result<()> {
     use i3ipc_jl::event::Event;
     match event {
@@ -274,9 +925,123 @@ pub fn handle_event(state: &crate::state::State, event: &i3ipc_jl::event::Event)
             slog_scope::debug!("Workspace event: {:?}", workspace_event);
             workspace_handler::handle_event(state, event, workspace_event)
         }
+        Event::OutputEvent(output_event) => {
+            slog_scope::debug!("Output event: {:?}", output_event);
+            output_handler::handle_event(state, event, output_event)
+        }
+        Event::ModeEvent(mode_event) => {
+            slog_scope::debug!("Mode event: {:?}", mode_event);
+            mode_handler::handle_event(state, event, mode_event)
+        }
+        Event::BindingEvent(binding_event) => {
+            slog_scope::debug!("Binding event: {:?}", binding_event);
+            binding_handler::handle_event(state, event, binding_event)
+        }
+        Event::ShutdownEvent(shutdown_event) => {
+            slog_scope::debug!("Shutdown event: {:?}", shutdown_event);
+            shutdown_handler::handle_event(state, event, shutdown_event)
+        }
         other => {
             slog_scope::warn!("Got unexpected event: {:?}", other);
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::event_action::expand_placeholders;
+    use std::collections::HashMap;
+
+    fn placeholders() -> HashMap<String, String> {
+        [
+            ("container.id", "94000"),
+            ("container.title", "x\"; exec rm -rf ~; \""),
+            ("container.class", "Fire\\fox"),
+            ("container.instance", "org.gnome.Nautilus"),
+            ("container.role", ".*"),
+            ("container.mark", "a] kill; [class=b"),
+            ("container.name", "two words"),
+            ("current.name", "1; exec true"),
+            ("old.name", "line\nbreak"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect()
+    }
+
+    #[test]
+    fn known_placeholders_are_expanded() {
+        assert_eq!(
+            expand_placeholders("[con_id={container.id}] floating enable", &placeholders())
+                .unwrap(),
+            "[con_id=94000] floating enable"
+        );
+    }
+
+    #[test]
+    fn unknown_and_unterminated_placeholders_are_kept() {
+        assert_eq!(
+            expand_placeholders("mark {container.unknown} {container.id", &placeholders()).unwrap(),
+            "mark {container.unknown} {container.id"
+        );
+    }
+
+    #[test]
+    fn quoted_values_are_escaped() {
+        assert_eq!(
+            expand_placeholders(
+                "title_format \"{container.title}\"; mark \"{container.class}\"",
+                &placeholders()
+            )
+            .unwrap(),
+            "title_format \"x\\\"; exec rm -rf ~; \\\"\"; mark \"Fire\\\\fox\""
+        );
+    }
+
+    #[test]
+    fn hostile_values_are_rejected() {
+        // Outside of quotes
+        assert!(expand_placeholders("workspace {current.name}", &placeholders()).is_err());
+        assert!(expand_placeholders("title_format {container.title}", &placeholders()).is_err());
+        // After a closed quoted string
+        assert!(expand_placeholders("mark \"a\" {container.title}", &placeholders()).is_err());
+        // Control characters even inside of quotes
+        assert!(
+            expand_placeholders("rename workspace to \"{old.name}\"", &placeholders()).is_err()
+        );
+    }
+
+    #[test]
+    fn values_breaking_criteria_are_rejected() {
+        assert!(expand_placeholders("[con_mark={container.mark}] focus", &placeholders()).is_err());
+        assert!(expand_placeholders("[title={container.name}] focus", &placeholders()).is_err());
+        assert_eq!(
+            expand_placeholders("[con_mark=\"{container.mark}\"] focus", &placeholders()).unwrap(),
+            "[con_mark=\"a] kill; [class=b\"] focus"
+        );
+    }
+
+    #[test]
+    fn regex_values_are_escaped() {
+        assert_eq!(
+            expand_placeholders(
+                "[instance=\"^{container.instance|regex}$\" window_role=\"^{container.role|regex}$\"] kill",
+                &placeholders()
+            )
+            .unwrap(),
+            "[instance=\"^org\\\\.gnome\\\\.Nautilus$\" window_role=\"^\\\\.\\\\*$\"] kill"
+        );
+        // Escaped values contain `\`, so they can't be put outside of quotes
+        assert!(expand_placeholders(
+            "[instance={container.instance|regex}] kill",
+            &placeholders()
+        )
+        .is_err());
+        assert!(
+            expand_placeholders("[instance=\"{container.unknown|regex}\"]", &placeholders())
+                .unwrap()
+                .contains("{container.unknown|regex}")
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Rate limiting mode of an event handler
+#[derive(Clone, Copy, Debug)]
+pub enum RateLimit {
+    Unlimited,
+    /// Run only for the last event of a burst, after no events came for the given time
+    Debounce(Duration),
+    /// Run for at most one event per given time
+    Throttle(Duration),
+}
+
+impl RateLimit {
+    pub fn new(debounce_ms: Option<u64>, throttle_ms: Option<u64>) -> Self {
+        match (debounce_ms, throttle_ms) {
+            (Some(debounce_ms), _) => Self::Debounce(Duration::from_millis(debounce_ms)),
+            (None, Some(throttle_ms)) => Self::Throttle(Duration::from_millis(throttle_ms)),
+            (None, None) => Self::Unlimited,
+        }
+    }
+}
+
+struct PendingRun {
+    deadline: Instant,
+    job: Job,
+}
+
+/// State of a debounced handler
+pub enum Pending {
+    /// The burst is over, the job must be run now
+    Due(Job),
+    /// More events came, wait for the given time and check again
+    Wait(Duration),
+    /// Nothing to run
+    Empty,
+}
+
+/// Keeps throttle and debounce state of event handlers
+#[derive(Default)]
+pub struct RateLimiter {
+    last_run: HashMap<String, Instant>,
+    pending: HashMap<String, PendingRun>,
+}
+
+impl RateLimiter {
+    /// Returns true if the handler may run now. In that case the time of the run is remembered
+    pub fn throttle(&mut self, handler: &str, interval: Duration) -> bool {
+        let now = Instant::now();
+        match self.last_run.get(handler) {
+            Some(last_run) if now.duration_since(*last_run) < interval => false,
+            _ => {
+                self.last_run.insert(handler.to_owned(), now);
+                true
+            }
+        }
+    }
+
+    /// Schedules the job to run after the delay, replacing the job which is pending for the handler. Returns true if
+    /// nothing was pending, so the caller has to start waiting for the job with take_due()
+    pub fn debounce(&mut self, handler: &str, delay: Duration, job: Job) -> bool {
+        let pending = PendingRun {
+            deadline: Instant::now() + delay,
+            job,
+        };
+        self.pending.insert(handler.to_owned(), pending).is_none()
+    }
+
+    pub fn take_due(&mut self, handler: &str) -> Pending {
+        let now = Instant::now();
+        match self.pending.get(handler) {
+            None => Pending::Empty,
+            Some(pending) if pending.deadline > now => Pending::Wait(pending.deadline - now),
+            Some(_) => match self.pending.remove(handler) {
+                Some(pending) => Pending::Due(pending.job),
+                None => Pending::Empty,
+            },
+        }
+    }
+
+    pub fn cancel(&mut self, handler: &str) {
+        self.pending.remove(handler);
+    }
+
+    /// Forgets throttle state, e.g. after the config was reloaded and handlers were renumbered. Pending debounced jobs
+    /// are kept, they run with the actions they were scheduled with.
+    pub fn reset(&mut self) {
+        self.last_run.clear();
+    }
+}
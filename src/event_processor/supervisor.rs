@@ -0,0 +1,214 @@
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::config::LogLevel;
+
+/// How often a child with a timeout is checked for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the output is still collected after the shell exits. Programs started in background inherit the pipes, so
+/// they may stay open much longer
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Max size of the output kept for the log
+const MAX_OUTPUT_SIZE: usize = 64 * 1024;
+
+/// Output of a child pipe collected by a reader thread
+struct PipeReader {
+    output: Arc<Mutex<Vec<u8>>>,
+    /// Disconnected when the pipe is closed
+    closed: mpsc::Receiver<()>,
+}
+
+impl PipeReader {
+    /// Returns the output collected until the pipe is closed or the deadline passes
+    fn collect(self, deadline: Instant) -> String {
+        let _ = self
+            .closed
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        let output = self.output.lock().unwrap();
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
+
+/// Reads the pipe until it is closed. The pipe is never closed early: a program started in background would be killed
+/// by SIGPIPE on its next write. Output above MAX_OUTPUT_SIZE is read and dropped
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> PipeReader {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let (sender, closed) = mpsc::channel();
+    if let Some(mut pipe) = pipe {
+        let output = output.clone();
+        std::thread::spawn(move || {
+            let _sender = sender;
+            let mut buffer = [0; 4096];
+            loop {
+                match pipe.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(size) => {
+                        let mut output = output.lock().unwrap();
+                        let size =
+                            std::cmp::min(size, MAX_OUTPUT_SIZE.saturating_sub(output.len()));
+                        output.extend_from_slice(&buffer[..size]);
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(err) => {
+                        slog_scope::warn!("Failed to read output of child process: {}", err);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+    PipeReader { output, closed }
+}
+
+/// Kills the whole process group of the child, so commands started by `sh -c` are killed too
+fn kill_process_group(child: &std::process::Child) {
+    let pid = child.id() as libc::pid_t;
+    // SAFETY: kill() has no memory safety requirements. The child is not reaped yet, so its pid (and the process group
+    // id, which is equal to the pid) can't be reused.
+    if unsafe { libc::kill(-pid, libc::SIGKILL) } != 0 {
+        slog_scope::warn!(
+            "Failed to kill process group {}: {}",
+            pid,
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+fn wait(
+    child: &mut std::process::Child,
+    timeout: Option<Duration>,
+) -> Result<(std::process::ExitStatus, bool)> {
+    let deadline = match timeout {
+        Some(timeout) => Instant::now() + timeout,
+        None => return Ok((child.wait()?, false)),
+    };
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(child);
+            return Ok((child.wait()?, true));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Waits for the child and logs its exit status and output. Returns an error if it failed or was killed by timeout
+fn supervise(
+    mut child: std::process::Child,
+    command: &str,
+    timeout: Option<Duration>,
+    output_log_level: LogLevel,
+) -> Result<()> {
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let (status, timed_out) = wait(&mut child, timeout)
+        .with_context(|| format!("Failed to wait for shell command {command:?}"))?;
+    // Don't wait for programs which the command started in background and which keep the pipes open
+    let deadline = Instant::now() + OUTPUT_GRACE_PERIOD;
+    let stdout = stdout.collect(deadline);
+    let stderr = stderr.collect(deadline);
+
+    let message = if timed_out {
+        format!(
+            "Shell command {command:?} killed after timeout of {:?}, stdout: {stdout:?}, stderr: {stderr:?}",
+            timeout.unwrap_or_default()
+        )
+    } else {
+        format!("Shell command {command:?} exited with {status}, stdout: {stdout:?}, stderr: {stderr:?}")
+    };
+
+    if timed_out || !status.success() {
+        let level = match output_log_level {
+            LogLevel::Critical | LogLevel::Error => output_log_level,
+            _ => LogLevel::Warning,
+        };
+        level.log(&message);
+        if timed_out {
+            anyhow::bail!("Shell command {:?} killed after timeout", command)
+        }
+        anyhow::bail!("Shell command {:?} exited with {}", command, status)
+    }
+    output_log_level.log(&message);
+    Ok(())
+}
+
+fn spawn_child(
+    mut command: std::process::Command,
+    description: &str,
+) -> Result<std::process::Child> {
+    command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("Failed to spawn shell command {description:?}"))
+}
+
+/// Runs the command in its own process group and waits for it. Fails if the command exits with a non-zero status or
+/// is killed by timeout
+pub fn run(
+    command: std::process::Command,
+    description: &str,
+    timeout: Option<Duration>,
+    output_log_level: LogLevel,
+) -> Result<()> {
+    let child = spawn_child(command, description)?;
+    supervise(child, description, timeout, output_log_level)
+}
+
+/// Spawns the command in its own process group and starts a thread which waits for it. on_exit gets the result of the
+/// command: an error if it exits with a non-zero status or is killed by timeout
+pub fn spawn(
+    command: std::process::Command,
+    description: &str,
+    timeout: Option<Duration>,
+    output_log_level: LogLevel,
+    on_exit: impl FnOnce(Result<()>) + Send + 'static,
+) -> Result<()> {
+    let child = spawn_child(command, description)?;
+    let description = description.to_owned();
+    std::thread::Builder::new()
+        .name(format!("supervisor-{}", child.id()))
+        .spawn(move || on_exit(supervise(child, &description, timeout, output_log_level)))
+        .context("Failed to start supervisor thread")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(command: &str) -> std::process::Command {
+        let mut process = std::process::Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    }
+
+    #[test]
+    fn exit_status_is_reported() {
+        assert!(run(shell("true"), "true", None, LogLevel::Debug).is_ok());
+        assert!(run(shell("exit 3"), "exit 3", None, LogLevel::Debug).is_err());
+        let timeout = Some(Duration::from_millis(100));
+        assert!(run(shell("sleep 5"), "sleep 5", timeout, LogLevel::Debug).is_err());
+    }
+
+    #[test]
+    fn background_programs_are_not_waited_for() {
+        let started = Instant::now();
+        let command = "echo started; sleep 5 &";
+        assert!(run(shell(command), command, None, LogLevel::Debug).is_ok());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+
+/// i3 IPC message header magic
+const IPC_MAGIC: &[u8; 6] = b"i3-ipc";
+
+/// i3 IPC message type of the SUBSCRIBE request
+const IPC_SUBSCRIBE: u32 = 2;
+
+/// i3 IPC message type of tick events. i3ipc-jl doesn't know about tick events and panics on them, so they are read
+/// over a separate connection
+const IPC_TICK_EVENT: u32 = 0x8000_0007;
+
+/// Tick event, sent when a client calls `i3-msg -t send_tick <payload>`
+#[derive(Deserialize, Debug)]
+pub struct TickEventInfo {
+    /// True for the tick sent right after subscribing, which carries an empty payload
+    #[serde(default)]
+    pub first: bool,
+    #[serde(default)]
+    pub payload: String,
+}
+
+/// Same lookup as in i3ipc-jl, which doesn't export it
+fn socket_path() -> Result<String> {
+    if let Ok(path) = std::env::var("I3SOCK") {
+        return Ok(path);
+    }
+    if let Ok(path) = std::env::var("SWAYSOCK") {
+        return Ok(path);
+    }
+    let output = std::process::Command::new("i3")
+        .arg("--get-socketpath")
+        .output()
+        .context("Failed to run i3 --get-socketpath")?;
+    if !output.status.success() {
+        anyhow::bail!("i3 --get-socketpath failed: {}", output.status)
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_owned())
+}
+
+fn send_message(stream: &mut UnixStream, message_type: u32, payload: &str) -> Result<()> {
+    let mut message = Vec::with_capacity(14 + payload.len());
+    message.extend_from_slice(IPC_MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    message.extend_from_slice(&message_type.to_le_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream
+        .write_all(&message)
+        .context("Failed to send message to window manager")
+}
+
+/// Returns the type and the payload of the next message
+fn receive_message(stream: &mut UnixStream) -> Result<(u32, String)> {
+    let mut header = [0_u8; 14];
+    stream
+        .read_exact(&mut header)
+        .context("Failed to receive message from window manager")?;
+    if &header[..6] != IPC_MAGIC {
+        anyhow::bail!("Unexpected message header {:?}", &header[..6])
+    }
+    let length = u32::from_le_bytes([header[6], header[7], header[8], header[9]]);
+    let message_type = u32::from_le_bytes([header[10], header[11], header[12], header[13]]);
+    let mut payload = vec![0_u8; length as usize];
+    stream
+        .read_exact(&mut payload)
+        .context("Failed to receive message from window manager")?;
+    Ok((message_type, String::from_utf8_lossy(&payload).into_owned()))
+}
+
+/// Connection subscribed to tick events
+pub struct TickListener {
+    stream: UnixStream,
+}
+
+impl TickListener {
+    pub fn connect() -> Result<Self> {
+        let path = socket_path()?;
+        let mut stream = UnixStream::connect(&path)
+            .with_context(|| format!("Failed to connect to window manager at {path:?}"))?;
+        send_message(&mut stream, IPC_SUBSCRIBE, r#"["tick"]"#)?;
+        let (message_type, payload) = receive_message(&mut stream)?;
+        let reply: serde_json::Value =
+            serde_json::from_str(&payload).context("Failed to parse reply to tick subscription")?;
+        if message_type != IPC_SUBSCRIBE || reply.get("success") != Some(&true.into()) {
+            anyhow::bail!("Window manager refused tick subscription: {}", payload)
+        }
+        Ok(Self { stream })
+    }
+
+    /// Waits for the next tick event. Fails only if the connection is lost
+    pub fn next(&mut self) -> Result<TickEventInfo> {
+        loop {
+            let (message_type, payload) = receive_message(&mut self.stream)?;
+            if message_type != IPC_TICK_EVENT {
+                slog_scope::debug!("Ignoring message of type {:#x}", message_type);
+                continue;
+            }
+            match serde_json::from_str(&payload) {
+                Ok(event) => return Ok(event),
+                Err(err) => slog_scope::error!("Failed to parse tick event {:?}: {}", payload, err),
+            }
+        }
+    }
+}
@@ -1,13 +1,234 @@
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::Write;
+use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 /// Key used for workspaces without a group
 const DEFAULT_GROUP_KEY: &str = "";
 
-fn get_state_file_path() -> Result<PathBuf> {
+/// Workspace history of groups. Version 1 of the file was a map of group keys to the last focused workspace
+const LAST_WORKSPACES_FILE: &str = "last_workspaces.json";
+
+/// Advisory lock held while the state files are read or written
+const LOCK_FILE: &str = "state.lock";
+
+/// History kept in memory by the daemon. It is read again when the file was changed by another process
+static IN_MEMORY: Mutex<Option<CachedHistory>> = Mutex::new(None);
+
+/// Current version of the history file format
+const HISTORY_VERSION: u32 = 2;
+
+/// Max number of workspaces remembered per group
+const MAX_WORKSPACE_HISTORY: usize = 10;
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+struct GroupHistory {
+    /// Workspaces, most recently focused first
+    #[serde(default)]
+    workspaces: Vec<i64>,
+    /// Workspace shown on each output
+    #[serde(default)]
+    outputs: BTreeMap<String, i64>,
+}
+
+impl GroupHistory {
+    fn push(&mut self, workspace: i64) {
+        self.workspaces.retain(|v| *v != workspace);
+        self.workspaces.insert(0, workspace);
+        self.workspaces.truncate(MAX_WORKSPACE_HISTORY);
+    }
+
+    fn last(&self) -> Option<i64> {
+        self.workspaces.first().copied()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct History {
+    version: u32,
+    #[serde(default)]
+    groups: BTreeMap<String, GroupHistory>,
+    /// Group keys, most recently focused first
+    #[serde(default)]
+    group_order: Vec<String>,
+    /// Key of the group whose workspaces are named with the current group name format
+    #[serde(default)]
+    labelled_group: String,
+    /// The file was written by a newer version of i3im, it is left as is and the history is kept in memory only
+    #[serde(skip)]
+    read_only: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            version: HISTORY_VERSION,
+            groups: BTreeMap::new(),
+            group_order: Vec::new(),
+            labelled_group: DEFAULT_GROUP_KEY.to_owned(),
+            read_only: false,
+        }
+    }
+}
+
+struct CachedHistory {
+    history: History,
+    /// Modification time of the file when it was last read or written
+    modified: Option<std::time::SystemTime>,
+}
+
+impl CachedHistory {
+    /// Reads the history. The caller must hold the lock
+    fn read() -> Self {
+        Self {
+            history: History::read(),
+            modified: modified(),
+        }
+    }
+
+    /// Reads the history again if the file was changed since. The caller must hold the lock
+    fn refresh(&mut self) {
+        if modified() != self.modified {
+            slog_scope::debug!(
+                "Workspace history was changed by another process, reading it again"
+            );
+            *self = Self::read()
+        }
+    }
+}
+
+/// Content of the history file
+enum Parsed {
+    /// History of the current version
+    Current(History),
+    /// History converted from version 1, to be saved in the current version
+    Migrated(History),
+    /// File of a newer i3im with the given version, which must not be overwritten
+    Newer(u64),
+}
+
+impl History {
+    fn group(&self, group: Option<&str>) -> Option<&GroupHistory> {
+        self.groups.get(&group_to_key(group))
+    }
+
+    fn group_mut(&mut self, group: Option<&str>) -> &mut GroupHistory {
+        self.groups.entry(group_to_key(group)).or_default()
+    }
+
+    fn push_group(&mut self, group: Option<&str>) {
+        let key = group_to_key(group);
+        self.group_order.retain(|v| v != &key);
+        self.group_order.insert(0, key);
+    }
+
+    /// Converts the file of version 1
+    fn migrate(last_workspaces: HashMap<String, i64>) -> Self {
+        let mut history = Self::default();
+        for (key, workspace) in last_workspaces {
+            history.groups.entry(key).or_default().push(workspace);
+        }
+        history
+    }
+
+    /// Parses the content of the history file. Version 1 is tried first: "version" is a valid group name there, so
+    /// a map of numbers is never taken for a later version
+    fn parse(value: serde_json::Value) -> Result<Parsed> {
+        if let Ok(last_workspaces) = serde_json::from_value::<HashMap<String, i64>>(value.clone()) {
+            return Ok(Parsed::Migrated(Self::migrate(last_workspaces)));
+        }
+        let version = value
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .context("Workspace history has no version")?;
+        if version > u64::from(HISTORY_VERSION) {
+            return Ok(Parsed::Newer(version));
+        }
+        if version != u64::from(HISTORY_VERSION) {
+            anyhow::bail!("Unsupported workspace history version {version}")
+        }
+        if !value.get("groups").map_or(false, |v| v.is_object()) {
+            anyhow::bail!("Workspace history has no groups")
+        }
+        Ok(Parsed::Current(serde_json::from_value(value)?))
+    }
+
+    /// Reads the history, migrating older formats. The caller must hold the lock
+    fn read() -> Self {
+        let value: Option<serde_json::Value> = load(LAST_WORKSPACES_FILE);
+        let result = match value.map(Self::parse) {
+            Some(Ok(Parsed::Current(history))) => Ok(history),
+            Some(Ok(Parsed::Migrated(history))) => {
+                slog_scope::info!("Migrated workspace history to version {HISTORY_VERSION}");
+                history.save();
+                Ok(history)
+            }
+            Some(Ok(Parsed::Newer(version))) => {
+                slog_scope::warn!(
+                    "Workspace history {} has version {} of a newer i3im, keeping the history in memory only",
+                    LAST_WORKSPACES_FILE,
+                    version
+                );
+                Ok(Self {
+                    read_only: true,
+                    ..Self::default()
+                })
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(Self::default()),
+        };
+        result.unwrap_or_else(|e| {
+            quarantine(LAST_WORKSPACES_FILE, &e);
+            Self::default()
+        })
+    }
+
+    fn load() -> Self {
+        let mut cached = IN_MEMORY.lock().unwrap();
+        let _lock = lock();
+        match cached.as_mut() {
+            Some(cached) => {
+                cached.refresh();
+                cached.history.clone()
+            }
+            None => Self::read(),
+        }
+    }
+
+    fn save(&self) {
+        if self.read_only {
+            slog_scope::debug!("Not saving workspace history over the one of a newer i3im");
+            return;
+        }
+        if let Err(e) = save(LAST_WORKSPACES_FILE, self) {
+            slog_scope::warn!("Failed to save workspace history: {:?}", e);
+        }
+    }
+
+    /// Loads the history, applies the change and saves the result
+    fn update<R>(change: impl FnOnce(&mut Self) -> R) -> R {
+        let mut cached = IN_MEMORY.lock().unwrap();
+        let _lock = lock();
+        if let Some(cached) = cached.as_mut() {
+            cached.refresh();
+            let result = change(&mut cached.history);
+            cached.history.save();
+            cached.modified = modified();
+            return result;
+        }
+        let mut history = Self::read();
+        let result = change(&mut history);
+        history.save();
+        result
+    }
+}
+
+fn get_state_file_path(file_name: &str) -> Result<PathBuf> {
     let state_home = std::env::var("XDG_STATE_HOME")
         .map(PathBuf::from)
         .or_else(|_| {
@@ -15,37 +236,113 @@ fn get_state_file_path() -> Result<PathBuf> {
                 .map(|home| PathBuf::from(home).join(".local/state"))
                 .map_err(|_| anyhow::anyhow!("Neither XDG_STATE_HOME nor HOME is set"))
         })?;
-    let path = state_home.join("i3im/last_workspaces.json");
+    let path = state_home.join("i3im").join(file_name);
     slog_scope::debug!("State file path: {:?}", path);
     Ok(path)
 }
 
-fn load() -> HashMap<String, i64> {
-    let path = match get_state_file_path() {
+fn load<T: DeserializeOwned + Default + std::fmt::Debug>(file_name: &str) -> T {
+    let path = match get_state_file_path(file_name) {
         Ok(p) => p,
         Err(e) => {
             slog_scope::warn!("Failed to get state file path: {:?}", e);
-            return HashMap::new();
+            return T::default();
         }
     };
 
     match fs::read_to_string(&path) {
         Ok(content) => {
             slog_scope::debug!("Loaded state file content: {}", content);
-            let state: HashMap<String, i64> = serde_json::from_str(&content).unwrap_or_default();
-            slog_scope::debug!("Parsed state: {:?}", state);
-            state
+            match serde_json::from_str(&content) {
+                Ok(state) => {
+                    slog_scope::debug!("Parsed state: {:?}", state);
+                    state
+                }
+                Err(e) => {
+                    quarantine(file_name, &e.into());
+                    T::default()
+                }
+            }
         }
         Err(e) => {
             slog_scope::debug!("Failed to read state file: {:?}", e);
-            HashMap::new()
+            T::default()
+        }
+    }
+}
+
+/// Returns the modification time of the history file, None if it doesn't exist
+fn modified() -> Option<std::time::SystemTime> {
+    get_state_file_path(LAST_WORKSPACES_FILE)
+        .ok()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok())
+}
+
+/// Moves a state file which can't be parsed out of the way, so it is kept for inspection instead of being overwritten
+fn quarantine(file_name: &str, error: &anyhow::Error) {
+    let result = get_state_file_path(file_name).and_then(|path| {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|v| v.as_secs())
+            .unwrap_or_default();
+        let target = path.with_extension(format!("json.corrupt-{timestamp}"));
+        fs::rename(&path, &target).context("Failed to move corrupt state file")?;
+        Ok(target)
+    });
+    match result {
+        Ok(target) => slog_scope::error!(
+            "State file {} is corrupt ({:#}), moved to {:?}",
+            file_name,
+            error,
+            target
+        ),
+        Err(e) => slog_scope::error!(
+            "State file {} is corrupt ({:#}), failed to move it away: {:?}",
+            file_name,
+            error,
+            e
+        ),
+    }
+}
+
+/// Takes the exclusive advisory lock of the state files. The lock is released when the returned file is closed. If
+/// locking fails, the caller proceeds unlocked rather than losing the update
+fn lock() -> Option<fs::File> {
+    let result = get_state_file_path(LOCK_FILE).and_then(|path| {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create state directory")?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .context("Failed to open state lock file")?;
+        loop {
+            // SAFETY: flock() has no memory safety requirements. The descriptor is owned by file, which stays open
+            // during the call.
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+                return Ok(file);
+            }
+            let e = std::io::Error::last_os_error();
+            if e.kind() != std::io::ErrorKind::Interrupted {
+                return Err(e).context("Failed to lock state files");
+            }
+        }
+    });
+    match result {
+        Ok(file) => Some(file),
+        Err(e) => {
+            slog_scope::warn!("Proceeding without state lock: {:?}", e);
+            None
         }
     }
 }
 
 /// Atomically saves the state to disk using write-to-temp-then-rename pattern
-fn save(state: &HashMap<String, i64>) -> Result<()> {
-    let path = get_state_file_path()?;
+fn save<T: Serialize>(file_name: &str, state: &T) -> Result<()> {
+    let path = get_state_file_path(file_name)?;
 
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).context("Failed to create state directory")?;
@@ -54,8 +351,14 @@ fn save(state: &HashMap<String, i64>) -> Result<()> {
     let content = serde_json::to_string_pretty(state).context("Failed to serialize state")?;
     slog_scope::debug!("Saving state: {}", content);
 
-    // Atomic write: write to temp file, then rename
-    let temp_path = path.with_extension("json.tmp");
+    // Atomic write: write to temp file, then rename. The temp file name is unique, so concurrent writers don't clobber
+    // each other's files
+    static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let temp_path = path.with_extension(format!(
+        "json.{}.{}.tmp",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
     let mut file = fs::File::create(&temp_path).context("Failed to create temporary state file")?;
     file.write_all(content.as_bytes())
         .context("Failed to write to temporary state file")?;
@@ -70,52 +373,176 @@ fn group_to_key(group: Option<&str>) -> String {
     group.unwrap_or(DEFAULT_GROUP_KEY).to_string()
 }
 
-/// Updates the last workspace for current_group and returns the last workspace for target_group.
-/// This is more efficient than separate set + get calls as it only reads/writes the file once.
-pub fn update_and_get(
+fn key_to_group(key: String) -> Option<String> {
+    if key == DEFAULT_GROUP_KEY {
+        None
+    } else {
+        Some(key)
+    }
+}
+
+/// Keeps the history in memory for the rest of the process lifetime, so it is read only when another process changes it
+pub fn keep_in_memory() {
+    let mut cached = IN_MEMORY.lock().unwrap();
+    let _lock = lock();
+    *cached = Some(CachedHistory::read());
+}
+
+/// What is remembered about the group being switched to
+pub struct GroupSwitch {
+    /// Last focused workspace of the group
+    pub last_workspace: Option<i64>,
+    /// Workspaces of the group shown on outputs
+    pub output_workspaces: HashMap<String, i64>,
+}
+
+/// Saves the state of current_group when switching to target_group and returns the state of target_group. The whole
+/// switch is one read and one write of the history: current_workspace goes to the workspace history of current_group,
+/// current_outputs to its workspaces shown on outputs, and both groups to the top of the group history.
+pub fn switch_group(
     current_group: Option<&str>,
-    current_workspace: i64,
+    current_workspace: Option<i64>,
+    current_outputs: &HashMap<String, i64>,
     target_group: Option<&str>,
-) -> Option<i64> {
+) -> GroupSwitch {
     slog_scope::debug!(
-        "update_and_get: current_group={:?}, current_workspace={}, target_group={:?}",
+        "switch_group: current_group={:?}, current_workspace={:?}, target_group={:?}",
         current_group,
         current_workspace,
         target_group
     );
+    History::update(|history| {
+        let group_history = history.group_mut(current_group);
+        if let Some(current_workspace) = current_workspace {
+            group_history.push(current_workspace);
+        }
+        group_history
+            .outputs
+            .extend(current_outputs.iter().map(|(k, v)| (k.clone(), *v)));
+        history.push_group(current_group);
+        history.push_group(target_group);
+        let target = history.group(target_group);
+        let result = GroupSwitch {
+            last_workspace: target.and_then(|v| v.last()),
+            output_workspaces: target
+                .map(|v| v.outputs.clone().into_iter().collect())
+                .unwrap_or_default(),
+        };
+        slog_scope::debug!(
+            "switch_group: last_workspace={:?}, output_workspaces={:?}",
+            result.last_workspace,
+            result.output_workspaces
+        );
+        result
+    })
+}
+
+pub fn get_last_workspace(group: Option<&str>) -> Option<i64> {
+    let result = History::load().group(group).and_then(|v| v.last());
+    slog_scope::debug!("get_last_workspace: group={:?}, result={:?}", group, result);
+    result
+}
 
-    let mut state = load();
+/// Moves target_workspace to the top of the workspace history of the group, right after current_workspace is moved
+/// there
+pub fn record_workspace_switch(group: Option<&str>, current_workspace: i64, target_workspace: i64) {
+    History::update(|history| {
+        let group_history = history.group_mut(group);
+        group_history.push(current_workspace);
+        group_history.push(target_workspace);
+    })
+}
 
-    // Save current workspace for current group
-    let current_key = group_to_key(current_group);
-    state.insert(current_key, current_workspace);
+/// Returns workspaces of the group, most recently focused first
+pub fn get_workspace_history(group: Option<&str>) -> Vec<i64> {
+    History::load()
+        .group(group)
+        .map(|v| v.workspaces.clone())
+        .unwrap_or_default()
+}
 
-    // Get last workspace for target group
-    let target_key = group_to_key(target_group);
-    let result = state.get(&target_key).copied();
+/// Returns groups, most recently focused first
+pub fn get_group_history() -> Vec<Option<String>> {
+    History::load()
+        .group_order
+        .into_iter()
+        .map(key_to_group)
+        .collect()
+}
 
-    // Save state
-    if let Err(e) = save(&state) {
-        slog_scope::warn!("Failed to save state: {:?}", e);
-    }
+/// Removes the group from the history
+pub fn forget_group(group: Option<&str>) {
+    let key = group_to_key(group);
+    History::update(|history| {
+        history.groups.remove(&key);
+        history.group_order.retain(|v| v != &key);
+    })
+}
 
-    slog_scope::debug!(
-        "update_and_get: target_key={:?}, result={:?}",
-        target_key,
-        result
-    );
-    result
+pub fn get_labelled_group() -> Option<String> {
+    key_to_group(History::load().labelled_group)
 }
 
-pub fn get_last_workspace(group: Option<&str>) -> Option<i64> {
-    let state = load();
+pub fn set_labelled_group(group: Option<&str>) {
     let key = group_to_key(group);
-    let result = state.get(&key).copied();
-    slog_scope::debug!(
-        "get_last_workspace: group={:?}, key={:?}, result={:?}",
-        group,
-        key,
-        result
-    );
-    result
+    if History::load().labelled_group != key {
+        History::update(|history| history.labelled_group = key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_workspaces_are_migrated() {
+        let history = History::migrate(HashMap::from([
+            (DEFAULT_GROUP_KEY.to_owned(), 3),
+            ("work".to_owned(), 7),
+        ]));
+        assert_eq!(history.version, HISTORY_VERSION);
+        assert_eq!(history.group(None).and_then(|v| v.last()), Some(3));
+        assert_eq!(history.group(Some("work")).and_then(|v| v.last()), Some(7));
+        assert_eq!(history.group(Some("other")).map(|v| v.last()), None);
+        assert!(history.group_order.is_empty());
+        assert_eq!(history.labelled_group, DEFAULT_GROUP_KEY);
+    }
+
+    fn parse(value: serde_json::Value) -> Parsed {
+        History::parse(value).unwrap()
+    }
+
+    #[test]
+    fn version_group_of_last_workspaces_is_migrated() {
+        for version in [2, 7] {
+            match parse(serde_json::json!({"version": version, "work": 3})) {
+                Parsed::Migrated(history) => {
+                    assert_eq!(history.group(Some("work")).and_then(|v| v.last()), Some(3));
+                    assert_eq!(
+                        history.group(Some("version")).and_then(|v| v.last()),
+                        Some(version)
+                    );
+                }
+                _ => panic!("version {version} is not migrated"),
+            }
+        }
+    }
+
+    #[test]
+    fn versioned_history_is_detected() {
+        let history =
+            serde_json::to_value(History::migrate(HashMap::from([("work".to_owned(), 3)])))
+                .unwrap();
+        match parse(history) {
+            Parsed::Current(history) => {
+                assert_eq!(history.group(Some("work")).and_then(|v| v.last()), Some(3))
+            }
+            _ => panic!("history of the current version is not detected"),
+        }
+        assert!(matches!(
+            parse(serde_json::json!({"version": 3, "groups": {}, "new_field": []})),
+            Parsed::Newer(3)
+        ));
+        assert!(History::parse(serde_json::json!({"version": 2, "work": [3]})).is_err());
+    }
 }
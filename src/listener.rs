@@ -2,15 +2,148 @@
 use clap::Args;
 use std::sync::Mutex;
 
+/// How often the config file is checked for changes in --watch-config mode
+const CONFIG_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Delay before the first reconnection attempt, doubled after every failed attempt
+const RECONNECT_INITIAL_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Upper limit for the delay between reconnection attempts
+const RECONNECT_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Give up reconnecting if the window manager is not back after this time
+const RECONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
 #[derive(Args, Clone)]
-pub struct ListenerCmd {}
+pub struct ListenerCmd {
+    /// Reload the config file when it changes. Independently of this option, the config is reloaded on SIGHUP
+    #[clap(long)]
+    watch_config: bool,
+    /// Don't accept commands from clients on the socket
+    #[clap(long)]
+    no_socket: bool,
+}
 
 impl ListenerCmd {
-    pub fn run(&self, state: crate::state::State) -> Result<()> {
+    pub fn run(
+        &self,
+        state: crate::state::State,
+        config_path: &str,
+        handler: crate::daemon::Handler,
+    ) -> Result<()> {
+        spawn_sighup_reloader(state.clone(), config_path.to_owned())?;
+        if self.watch_config {
+            spawn_config_watcher(state.clone(), config_path.to_owned())?;
+        }
+        spawn_tick_listener(state.clone())?;
+        if !self.no_socket {
+            if let Err(err) =
+                crate::daemon::spawn_server(state.clone(), config_path.to_owned(), handler)
+            {
+                slog_scope::warn!("Commands from clients are not accepted: {:?}", err);
+            }
+        }
         Listener::new(state)?.run()
     }
 }
 
+/// Reads and validates the config file and makes it active. If it is broken, the old config stays active
+fn reload_config(state: &crate::state::State, config_path: &str) {
+    match crate::config::Config::read(config_path) {
+        Ok(config) => {
+            state.replace_config(config);
+            slog_scope::info!("Config {:?} reloaded", config_path);
+        }
+        Err(err) => {
+            slog_scope::error!(
+                "Failed to reload config, keeping the old one active: {:?}",
+                err
+            );
+        }
+    }
+}
+
+fn spawn_sighup_reloader(state: crate::state::State, config_path: String) -> Result<()> {
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+    std::thread::Builder::new()
+        .name("sighup-reloader".to_owned())
+        .spawn(move || {
+            for _ in signals.forever() {
+                slog_scope::info!("Got SIGHUP, reloading config");
+                reload_config(&state, &config_path);
+            }
+        })?;
+    Ok(())
+}
+
+fn config_mtime(config_path: &str) -> Option<std::time::SystemTime> {
+    std::fs::metadata(config_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn spawn_config_watcher(state: crate::state::State, config_path: String) -> Result<()> {
+    std::thread::Builder::new()
+        .name("config-watcher".to_owned())
+        .spawn(move || {
+            let mut last_mtime = config_mtime(&config_path);
+            loop {
+                std::thread::sleep(CONFIG_WATCH_INTERVAL);
+                let mtime = config_mtime(&config_path);
+                if mtime.is_some() && mtime != last_mtime {
+                    slog_scope::info!("Config {:?} changed, reloading", config_path);
+                    reload_config(&state, &config_path);
+                }
+                last_mtime = mtime;
+            }
+        })?;
+    Ok(())
+}
+
+/// Reads tick events over a separate connection, because i3ipc-jl can't parse them. The connection is restored when it
+/// is lost, the listener of other events decides when to stop
+fn spawn_tick_listener(state: crate::state::State) -> Result<()> {
+    std::thread::Builder::new()
+        .name("tick-listener".to_owned())
+        .spawn(move || {
+            let mut delay = RECONNECT_INITIAL_DELAY;
+            loop {
+                match crate::event_processor::tick::TickListener::connect() {
+                    Ok(mut tick_listener) => {
+                        delay = RECONNECT_INITIAL_DELAY;
+                        let err = loop {
+                            match tick_listener.next() {
+                                Ok(event) => crate::event_processor::processor::handle_tick_event(
+                                    &state, &event,
+                                ),
+                                Err(err) => break err,
+                            }
+                        };
+                        slog_scope::warn!("Lost tick events connection: {:?}", err);
+                    }
+                    Err(err) => {
+                        slog_scope::warn!(
+                            "Failed to subscribe to tick events, retrying in {:?}: {:?}",
+                            delay,
+                            err
+                        );
+                    }
+                }
+                std::thread::sleep(delay);
+                delay = std::cmp::min(delay * 2, RECONNECT_MAX_DELAY);
+            }
+        })?;
+    Ok(())
+}
+
+/// Why the listener stopped reading events
+enum ListenOutcome {
+    /// The window manager exits, nothing to listen anymore
+    Exit,
+    /// The window manager restarts or the connection was lost
+    Reconnect,
+}
+
 struct Listener {
     i3_listener: Mutex<i3ipc_jl::I3EventListener>,
     state: crate::state::State,
@@ -29,16 +162,36 @@ fn handle_event(&self, event: &i3ipc_jl::event::Event) -> Result<()> {
         crate::event_processor::processor::handle_event(&self.state, event)
     }
 
-    pub fn run(&self) -> Result<()> {
+    fn subscribe(&self) -> Result<()> {
         self.i3_listener.lock().unwrap().subscribe(&[
             i3ipc_jl::Subscription::Window,
             i3ipc_jl::Subscription::Workspace,
+            i3ipc_jl::Subscription::Output,
+            i3ipc_jl::Subscription::Mode,
+            i3ipc_jl::Subscription::Binding,
+            i3ipc_jl::Subscription::Shutdown,
         ])?;
+        Ok(())
+    }
 
+    fn listen(&self) -> Result<ListenOutcome> {
         for event in self.i3_listener.lock().unwrap().listen() {
             match &event {
                 Ok(event) => {
-                    self.handle_event(event)?;
+                    if let Err(err) = self.handle_event(event) {
+                        slog_scope::error!("Failed to handle event: {:?}", err);
+                    }
+                    if let i3ipc_jl::event::Event::ShutdownEvent(shutdown_event) = event {
+                        use i3ipc_jl::event::inner::ShutdownChange;
+                        return Ok(match shutdown_event.change {
+                            ShutdownChange::Exit => ListenOutcome::Exit,
+                            _ => ListenOutcome::Reconnect,
+                        });
+                    }
+                }
+                Err(i3ipc_jl::MessageError::Receive(err)) => {
+                    slog_scope::error!("Lost connection to window manager: {}", err);
+                    return Ok(ListenOutcome::Reconnect);
                 }
                 Err(err) => {
                     slog_scope::error!("{}", err);
@@ -46,6 +199,57 @@ pub fn run(&self) -> Result<()> {
             }
         }
 
-        Ok(())
+        Ok(ListenOutcome::Reconnect)
+    }
+
+    fn try_reconnect(&self) -> Result<()> {
+        *self.i3_listener.lock().unwrap() = i3ipc_jl::I3EventListener::connect()?;
+        self.subscribe()?;
+        self.state.reconnect_i3()
+    }
+
+    /// Reconnects both the event and the command connections, retrying with exponential backoff
+    fn reconnect(&self) -> Result<()> {
+        let started = std::time::Instant::now();
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        loop {
+            std::thread::sleep(delay);
+            match self.try_reconnect() {
+                Ok(()) => {
+                    slog_scope::info!("Reconnected to window manager");
+                    return Ok(());
+                }
+                Err(err) if started.elapsed() >= RECONNECT_TIMEOUT => {
+                    return Err(err.context(format!(
+                        "Failed to reconnect to window manager in {RECONNECT_TIMEOUT:?}"
+                    )));
+                }
+                Err(err) => {
+                    delay = std::cmp::min(delay * 2, RECONNECT_MAX_DELAY);
+                    slog_scope::warn!(
+                        "Failed to reconnect to window manager, retrying in {:?}: {:?}",
+                        delay,
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        self.subscribe()?;
+
+        loop {
+            match self.listen()? {
+                ListenOutcome::Exit => {
+                    slog_scope::info!("Window manager exits, stopping listener");
+                    return Ok(());
+                }
+                ListenOutcome::Reconnect => {
+                    slog_scope::info!("Reconnecting to window manager");
+                    self.reconnect()?
+                }
+            }
+        }
     }
 }
@@ -5,11 +5,13 @@
 
 mod commands;
 mod config;
+mod daemon;
 mod event_processor;
 mod last_workspaces;
 mod listener;
 mod state;
 mod workspace_group;
+mod workspace_name;
 
 const CONFIG_DEFAULT_PATH: &str = "~/.config/i3im.yaml";
 
@@ -22,6 +24,8 @@ enum ConfigCommand {
     Documentation,
     /// Generate default config
     Generate,
+    /// Check config file and exit with an error if it is invalid
+    Validate,
 }
 
 impl ConfigCommand {
@@ -48,11 +52,23 @@ fn config_dump(config_path: &Option<String>) {
         println!("{}", serde_yaml::to_string(&config).unwrap());
     }
 
+    fn config_validate(config_path: &Option<String>) {
+        let config_path = config_path
+            .clone()
+            .unwrap_or(shellexpand::tilde(CONFIG_DEFAULT_PATH).to_string());
+        if let Err(e) = config::Config::read(&config_path) {
+            eprintln!("{e:#}");
+            std::process::exit(1)
+        }
+        println!("{config_path}: OK");
+    }
+
     pub fn run(&self, config_path: &Option<String>) {
         match self {
             ConfigCommand::Dump => Self::config_dump(config_path),
             ConfigCommand::Documentation => Self::config_documentation(),
             ConfigCommand::Generate => Self::config_generate(),
+            ConfigCommand::Validate => Self::config_validate(config_path),
         }
     }
 }
@@ -75,10 +91,35 @@ enum CommandLine {
     /// Move operations
     #[command(subcommand)]
     Move(crate::commands::Move),
+    /// Group operations
+    #[command(subcommand)]
+    Group(crate::commands::Group),
     /// Run listener
     Listen(crate::listener::ListenerCmd),
 }
 
+impl CommandLine {
+    /// Runs a command which works with the window manager
+    fn run_with_state(&self, state: crate::state::State) -> Result<()> {
+        match self {
+            CommandLine::Focus(cmd) => cmd.run(state),
+            CommandLine::List(cmd) => cmd.run(state),
+            CommandLine::Rename(cmd) => cmd.run(state),
+            CommandLine::Move(cmd) => cmd.run(state),
+            CommandLine::Group(cmd) => cmd.run(state),
+            CommandLine::Config(_) | CommandLine::Listen(_) => {
+                anyhow::bail!("The command can't be run by the daemon")
+            }
+        }
+    }
+}
+
+/// Runs a command forwarded to the daemon by a client
+fn run_forwarded(state: crate::state::State, args: Vec<String>) -> Result<()> {
+    let application = Application::try_parse_from(args)?;
+    application.command.run_with_state(state)
+}
+
 /// Example of simple cli program
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -125,12 +166,14 @@ fn init_logger(&self, config: &config::Config) -> Result<()> {
         Ok(())
     }
 
-    fn init_config(&self) -> Result<crate::config::Config> {
-        let config_path = self
-            .config_path
+    fn config_path(&self) -> String {
+        self.config_path
             .clone()
-            .unwrap_or(shellexpand::tilde(CONFIG_DEFAULT_PATH).to_string());
-        let config = config::Config::read(&config_path)?;
+            .unwrap_or(shellexpand::tilde(CONFIG_DEFAULT_PATH).to_string())
+    }
+
+    fn init_config(&self) -> Result<crate::config::Config> {
+        let config = config::Config::read(&self.config_path())?;
         self.init_logger(&config)?;
         Ok(config)
     }
@@ -147,25 +190,16 @@ fn run_command(&self) -> Result<()> {
                 cmd.run(&self.config_path);
                 Ok(())
             }
-            CommandLine::Focus(cmd) => {
-                let state = self.init_state()?;
-                cmd.run(state)
-            }
-            CommandLine::List(cmd) => {
-                let state = self.init_state()?;
-                cmd.run(state)
-            }
-            CommandLine::Rename(cmd) => {
-                let state = self.init_state()?;
-                cmd.run(state)
-            }
-            CommandLine::Move(cmd) => {
+            CommandLine::Listen(listener) => {
                 let state = self.init_state()?;
-                cmd.run(state)
+                listener.run(state, &self.config_path(), run_forwarded)
             }
-            CommandLine::Listen(listener) => {
+            command => {
+                if crate::daemon::forward(&self.config_path())? {
+                    return Ok(());
+                }
                 let state = self.init_state()?;
-                listener.run(state)
+                command.run_with_state(state)
             }
         }
     }
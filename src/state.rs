@@ -2,9 +2,14 @@
 
 use anyhow::Result;
 
+#[derive(Clone)]
 pub struct State {
     i3connection: Arc<Mutex<i3ipc_jl::I3Connection>>,
-    config: Arc<Mutex<crate::config::Config>>,
+    config: Arc<Mutex<Arc<crate::config::Config>>>,
+    handler_failures: Arc<Mutex<crate::event_processor::failures::HandlerFailures>>,
+    rate_limiter: Arc<Mutex<crate::event_processor::rate_limit::RateLimiter>>,
+    /// Output of a command forwarded to the daemon, None when printing to stdout
+    output: Option<Arc<Mutex<Vec<String>>>>,
 }
 
 impl State {
@@ -12,11 +17,21 @@ pub fn new(config: crate::config::Config) -> Result<Self> {
         let i3connection = i3ipc_jl::I3Connection::connect()?;
         let r = Self {
             i3connection: Arc::new(Mutex::new(i3connection)),
-            config: Arc::new(Mutex::new(config)),
+            config: Arc::new(Mutex::new(Arc::new(config))),
+            handler_failures: Arc::new(Mutex::new(Default::default())),
+            rate_limiter: Arc::new(Mutex::new(Default::default())),
+            output: None,
         };
         Ok(r)
     }
 
+    /// Opens a new command connection, e.g. after the window manager was restarted
+    pub fn reconnect_i3(&self) -> Result<()> {
+        let i3connection = i3ipc_jl::I3Connection::connect()?;
+        *self.i3connection.lock().unwrap() = i3connection;
+        Ok(())
+    }
+
     pub fn with_i3connection<CB, R>(&self, cb: CB) -> R
     where
         CB: Fn(&mut i3ipc_jl::I3Connection) -> R,
@@ -25,36 +40,151 @@ pub fn with_i3connection<CB, R>(&self, cb: CB) -> R
         cb(&mut connection)
     }
 
-    pub fn run_i3_command(&self, command: &str) -> Result<()> {
+    /// Runs the command and returns the outcomes of its `;`-separated parts
+    pub fn run_i3_command_outcomes(
+        &self,
+        command: &str,
+    ) -> Result<Vec<i3ipc_jl::reply::CommandOutcome>> {
         self.with_i3connection(|connection| {
             slog_scope::info!("Running i3 command: {}", command);
-            connection.run_command(command)?;
-            Ok(())
+            Ok(connection.run_command(command)?.outcomes)
         })
     }
 
+    /// Runs the command, failing if the window manager rejects any part of it
+    pub fn run_i3_command(&self, command: &str) -> Result<()> {
+        let errors = self
+            .run_i3_command_outcomes(command)?
+            .into_iter()
+            .filter(|outcome| !outcome.success)
+            .map(|outcome| outcome.error.unwrap_or_else(|| "unknown error".to_owned()))
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            anyhow::bail!("i3 command {:?} failed: {}", command, errors.join("; "))
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the state which collects printed lines instead of writing them to stdout
+    pub fn with_captured_output(&self) -> Self {
+        Self {
+            output: Some(Arc::new(Mutex::new(Vec::new()))),
+            ..self.clone()
+        }
+    }
+
+    /// Takes the lines collected by a state returned from with_captured_output()
+    pub fn take_output(&self) -> Vec<String> {
+        self.output
+            .as_ref()
+            .map(|output| std::mem::take(&mut *output.lock().unwrap()))
+            .unwrap_or_default()
+    }
+
+    /// Prints the line of command output
+    pub fn print(&self, line: &str) {
+        match &self.output {
+            Some(output) => output.lock().unwrap().push(line.to_owned()),
+            None => println!("{line}"),
+        }
+    }
+
     pub fn with_config<CB, R>(&self, cb: CB) -> R
     where
         CB: Fn(&crate::config::Config) -> R,
     {
-        let config = self.config.lock().unwrap();
+        // The callback gets a snapshot of the config, so long callbacks like event handlers don't block reloads and
+        // commands forwarded to the daemon
+        let config = self.config.lock().unwrap().clone();
         cb(&config)
     }
 
+    /// Returns the workspace name format matching the current names of workspaces. The group whose workspaces are named
+    /// with the current group name format is kept in the workspace history, so it is shared with other i3im processes
+    pub fn workspace_name_format(&self) -> crate::workspace_name::WorkspaceNameFormat {
+        let labelled_group = crate::last_workspaces::get_labelled_group();
+        self.workspace_name_format_for(labelled_group.as_deref())
+    }
+
+    /// Returns the workspace name format for the case when current_group is focused
+    pub fn workspace_name_format_for(
+        &self,
+        current_group: Option<&str>,
+    ) -> crate::workspace_name::WorkspaceNameFormat {
+        self.with_config(|config| {
+            config
+                .workspace_name_format
+                .with_current_group(config.current_group_name_format.as_ref(), current_group)
+        })
+    }
+
+    pub fn set_labelled_group(&self, group: Option<&str>) {
+        crate::last_workspaces::set_labelled_group(group)
+    }
+
+    /// Replaces the active config. Handlers which are running at the moment finish with the old one
+    pub fn replace_config(&self, config: crate::config::Config) {
+        let mut current = self.config.lock().unwrap();
+        *current = Arc::new(config);
+        self.handler_failures.lock().unwrap().reset();
+        self.rate_limiter.lock().unwrap().reset();
+    }
+
+    pub fn with_handler_failures<CB, R>(&self, cb: CB) -> R
+    where
+        CB: Fn(&mut crate::event_processor::failures::HandlerFailures) -> R,
+    {
+        let mut handler_failures = self.handler_failures.lock().unwrap();
+        cb(&mut handler_failures)
+    }
+
+    pub fn with_rate_limiter<CB, R>(&self, cb: CB) -> R
+    where
+        CB: FnOnce(&mut crate::event_processor::rate_limit::RateLimiter) -> R,
+    {
+        let mut rate_limiter = self.rate_limiter.lock().unwrap();
+        cb(&mut rate_limiter)
+    }
+
     // Workspace history methods - delegate to last_workspaces module
 
-    /// Updates the last workspace for current_group and returns the last workspace for target_group.
-    /// This is more efficient than separate set + get calls.
-    pub fn update_and_get_last_workspace(
+    pub fn switch_group(
         &self,
         current_group: Option<&str>,
-        current_workspace: i64,
+        current_workspace: Option<i64>,
+        current_outputs: &std::collections::HashMap<String, i64>,
         target_group: Option<&str>,
-    ) -> Option<i64> {
-        crate::last_workspaces::update_and_get(current_group, current_workspace, target_group)
+    ) -> crate::last_workspaces::GroupSwitch {
+        crate::last_workspaces::switch_group(
+            current_group,
+            current_workspace,
+            current_outputs,
+            target_group,
+        )
     }
 
     pub fn get_last_workspace(&self, group: Option<&str>) -> Option<i64> {
         crate::last_workspaces::get_last_workspace(group)
     }
+
+    pub fn record_workspace_switch(
+        &self,
+        group: Option<&str>,
+        current_workspace: i64,
+        target_workspace: i64,
+    ) {
+        crate::last_workspaces::record_workspace_switch(group, current_workspace, target_workspace)
+    }
+
+    pub fn get_workspace_history(&self, group: Option<&str>) -> Vec<i64> {
+        crate::last_workspaces::get_workspace_history(group)
+    }
+
+    pub fn get_group_history(&self) -> Vec<Option<String>> {
+        crate::last_workspaces::get_group_history()
+    }
+
+    pub fn forget_group(&self, group: &str) {
+        crate::last_workspaces::forget_group(Some(group))
+    }
 }
@@ -1,8 +1,13 @@
 use anyhow::Result;
 
+use crate::workspace_name::WorkspaceNameFormat;
+
 /// Default workspace number when no history is available
 const DEFAULT_WORKSPACE: i64 = 1;
 
+/// Prefix of names given to workspaces while renames are swapping their names
+const TEMPORARY_NAME_PREFIX: &str = "i3im-tmp-";
+
 fn get_i3_workspaces(state: &crate::state::State) -> Result<Vec<i3ipc_jl::reply::Workspace>> {
     let r = state
         .with_i3connection(|conn| conn.get_workspaces())?
@@ -10,16 +15,162 @@ fn get_i3_workspaces(state: &crate::state::State) -> Result<Vec<i3ipc_jl::reply:
     Ok(r)
 }
 
-// 1           1 none none               JustI3ID
-// 1:web       1 "web" none              I3IDWithGroup
-// web         none "web" none           JustGroup
-// web:1       none "web" 1              GroupWithWorkspace
-// 1:web:1     1 "web" 1                 Full
-// other:web:1 none "other:web" 1        GroupWithWorkspace
-// other:web:a none "other:web:a" none   GroupWithWorkspace
-// 1:1         1 "1" none                I3IDWithGroup
-// aa:bb:cc    none "aa:bb" none         JustGroup
-#[derive(Clone)]
+fn get_i3_tree(state: &crate::state::State) -> Result<i3ipc_jl::reply::Node> {
+    let r = state.with_i3connection(|conn| conn.get_tree())?;
+    Ok(r)
+}
+
+fn collect_workspace_nodes<'a>(
+    node: &'a i3ipc_jl::reply::Node,
+    workspaces: &mut Vec<&'a i3ipc_jl::reply::Node>,
+) {
+    if node.nodetype == i3ipc_jl::reply::NodeType::Workspace {
+        workspaces.push(node);
+        return;
+    }
+    for child in &node.nodes {
+        collect_workspace_nodes(child, workspaces)
+    }
+}
+
+/// Returns names of workspaces which have at least one window
+fn get_non_empty_workspace_names(
+    state: &crate::state::State,
+) -> Result<std::collections::HashSet<String>> {
+    let tree = get_i3_tree(state)?;
+    let mut workspaces = Vec::new();
+    collect_workspace_nodes(&tree, &mut workspaces);
+    Ok(workspaces
+        .into_iter()
+        .filter(|w| !w.nodes.is_empty() || !w.floating_nodes.is_empty())
+        .filter_map(|w| w.name.clone())
+        .collect())
+}
+
+fn collect_window_names(node: &i3ipc_jl::reply::Node, names: &mut Vec<String>) {
+    if node.nodes.is_empty() && node.floating_nodes.is_empty() {
+        names.push(node.name.clone().unwrap_or_default());
+        return;
+    }
+    for child in node.nodes.iter().chain(node.floating_nodes.iter()) {
+        collect_window_names(child, names)
+    }
+}
+
+/// Hidden mark of the window which was focused when the group was left. Marks starting with an underscore are not
+/// shown in title bars
+fn focus_mark(group: Option<&str>) -> String {
+    format!("_i3im_focus:{}", group.unwrap_or_default())
+}
+
+/// Escapes the text to be put inside double quotes of an i3 command
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Runs the command, logging instead of failing if it doesn't apply, e.g. when its criteria match nothing
+fn run_optional_i3_command(state: &crate::state::State, command: &str) -> Result<()> {
+    for outcome in state.run_i3_command_outcomes(command)? {
+        if !outcome.success {
+            slog_scope::debug!("i3 command {:?} not applied: {:?}", command, outcome.error);
+        }
+    }
+    Ok(())
+}
+
+/// Marks the focused window as the one to focus when returning to the group. The mark is kept by the window manager,
+/// so it follows the window and disappears when the window is closed
+fn mark_focused_window(state: &crate::state::State, group: Option<&str>) -> Result<()> {
+    run_optional_i3_command(
+        state,
+        &format!("mark --add \"{}\"", quote(&focus_mark(group))),
+    )
+}
+
+/// Focuses the window marked by mark_focused_window(), if it is still on a workspace of the group
+fn focus_marked_window(
+    state: &crate::state::State,
+    workspaces: &[Workspace],
+    group: Option<&str>,
+) -> Result<()> {
+    let names = workspaces
+        .iter()
+        .filter(|w| w.id().group_matches(group))
+        .map(|w| regex::escape(w.name()))
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        return Ok(());
+    }
+    run_optional_i3_command(
+        state,
+        &format!(
+            "[con_mark=\"^{}$\" workspace=\"^({})$\"] focus",
+            quote(&regex::escape(&focus_mark(group))),
+            quote(&names.join("|"))
+        ),
+    )
+}
+
+/// Returns titles of windows per workspace name
+fn get_workspace_window_names(
+    state: &crate::state::State,
+) -> Result<std::collections::HashMap<String, Vec<String>>> {
+    let tree = get_i3_tree(state)?;
+    let mut workspaces = Vec::new();
+    collect_workspace_nodes(&tree, &mut workspaces);
+    Ok(workspaces
+        .into_iter()
+        .filter_map(|w| {
+            let mut names = Vec::new();
+            for child in w.nodes.iter().chain(w.floating_nodes.iter()) {
+                collect_window_names(child, &mut names)
+            }
+            w.name.clone().map(|name| (name, names))
+        })
+        .collect())
+}
+
+/// Direction for cycling through workspaces and groups
+#[derive(Clone, Copy, Debug)]
+pub enum Direction {
+    Next,
+    Prev,
+}
+
+impl Direction {
+    /// Returns the index of the neighbour of `position` in a list of `len` items
+    pub fn step(&self, position: usize, len: usize, wrap: bool) -> Option<usize> {
+        match self {
+            Self::Next if position + 1 < len => Some(position + 1),
+            Self::Next if wrap && len > 0 => Some(0),
+            Self::Prev if position > 0 => Some(position - 1),
+            Self::Prev if wrap && len > 0 => Some(len - 1),
+            _ => None,
+        }
+    }
+}
+
+/// Order of groups for cycling through them
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum GroupOrder {
+    /// Alphabetical, the same order as used for i3 workspace numbers
+    Name,
+    /// Most recently focused first
+    Recency,
+}
+
+// Parsing with the default name format "{id}:{group}:{n}":
+// 1             1 none none             JustI3ID
+// 1:web         1 "web" none            I3IDWithGroup
+// web           none "web" none         JustGroup
+// web:1         none "web" 1            GroupWithWorkspace
+// 1:web:1       1 "web" 1               Full
+// other:web:1   none "other:web" 1      GroupWithWorkspace (written by i3im as other%3Aweb:1)
+// other:web:a   none "other:web:a" none JustGroup
+// 1:1           1 "1" none              I3IDWithGroup (written by i3im as 1:%31)
+// 1:%32024:2    1 "2024" 2              Full
+// aa:bb:cc      none "aa:bb:cc" none    JustGroup
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum WorkspaceID {
     JustI3ID(i64),
     I3IDWithGroup(i64, String),
@@ -31,9 +182,10 @@ pub enum WorkspaceID {
 impl WorkspaceID {
     pub fn list(state: &crate::state::State) -> Result<Vec<Self>> {
         let workspaces = get_i3_workspaces(state)?;
+        let format = state.workspace_name_format();
         Ok(workspaces
             .into_iter()
-            .map(|w| Self::of_i3_workspace(&w.name))
+            .map(|w| Self::of_i3_workspace(&w.name, &format))
             .collect())
     }
 
@@ -51,18 +203,8 @@ pub fn is_just_i3_id(&self) -> bool {
         matches!(self, Self::JustI3ID(_))
     }
 
-    pub fn i3_workspace_name(&self) -> String {
-        match self {
-            Self::JustI3ID(i3_id) => format!("{i3_id}"),
-            Self::I3IDWithGroup(i3_id, group_name) => format!("{i3_id}:{group_name}"),
-            Self::JustGroup(group_name) => group_name.to_owned(),
-            Self::GroupWithWorkspace(group_name, group_workspace) => {
-                format!("{group_name}:{group_workspace}")
-            }
-            Self::Full(i3_id, group_name, group_workspace) => {
-                format!("{i3_id}:{group_name}:{group_workspace}")
-            }
-        }
+    pub fn i3_workspace_name(&self, format: &WorkspaceNameFormat) -> String {
+        format.format(self)
     }
 
     pub fn with_group(&self, group: Option<&str>) -> Self {
@@ -148,67 +290,34 @@ pub fn group_matches(&self, group: Option<&str>) -> bool {
         self.group().map(|s| s.as_str()) == group
     }
 
-    fn of_triplet(i3_id: &str, group_name: &str, group_workspace: &str) -> Self {
-        match (
-            i3_id.parse::<i64>().ok(),
-            group_workspace.parse::<i64>().ok(),
-        ) {
-            (Some(i3_id), Some(group_workspace)) => {
-                Self::Full(i3_id, group_name.to_string(), group_workspace)
-            }
-            (Some(i3_id), None) => {
-                Self::I3IDWithGroup(i3_id, format!("{group_name}:{group_workspace}"))
-            }
-            (None, Some(group_workspace)) => {
-                Self::GroupWithWorkspace(format!("{i3_id}:{group_name}"), group_workspace)
-            }
-            (None, None) => Self::JustGroup(format!("{i3_id}:{group_name}:{group_workspace}")),
-        }
-    }
-
-    pub fn of_i3_workspace(i3_workspace_name: &str) -> Self {
-        let fields = i3_workspace_name.split(':').collect::<Vec<_>>();
-        match *fields.as_slice() {
-            [i3_id, group_name, group_workspace] => {
-                Self::of_triplet(i3_id, group_name, group_workspace)
-            }
-            [field1, field2] => match (field1.parse::<i64>().ok(), field2.parse::<i64>().ok()) {
-                (Some(field1_num), _) => Self::I3IDWithGroup(field1_num, field2.to_owned()),
-                (None, Some(field2_num)) => Self::GroupWithWorkspace(field1.to_owned(), field2_num),
-                (None, None) => Self::JustGroup(i3_workspace_name.to_owned()),
-            },
-            [field] => match field.parse::<i64>().ok() {
-                Some(field_num) => Self::JustI3ID(field_num),
-                None => Self::JustGroup(i3_workspace_name.to_owned()),
-            },
-            [] => Self::JustGroup(String::new()),
-            _ => {
-                let first = fields.first().unwrap();
-                let last = fields.last().unwrap();
-                let middle = &fields[1..fields.len() - 1];
-                Self::of_triplet(first, &middle.join(":").to_owned(), last)
-            }
-        }
+    pub fn of_i3_workspace(i3_workspace_name: &str, format: &WorkspaceNameFormat) -> Self {
+        format.parse(i3_workspace_name)
     }
 
     pub fn focus(&self, state: &crate::state::State) -> Result<()> {
-        state.run_i3_command(&format!("workspace {}", self.i3_workspace_name()))?;
+        let format = state.workspace_name_format();
+        state.run_i3_command(&format!(
+            "workspace \"{}\"",
+            quote(&self.i3_workspace_name(&format))
+        ))?;
 
         Ok(())
     }
 
     pub fn rename(&self, state: &crate::state::State, new_id: &Self) -> Result<()> {
-        state.run_i3_command(&format!(
-            "rename workspace \"{}\" to \"{}\"",
-            self.i3_workspace_name(),
-            new_id.i3_workspace_name()
-        ))
+        let format = state.workspace_name_format();
+        rename_i3_workspace(
+            state,
+            &self.i3_workspace_name(&format),
+            &new_id.i3_workspace_name(&format),
+        )
     }
 
     pub fn move_container_to(&self, state: &crate::state::State) -> Result<()> {
+        let format = state.workspace_name_format();
         state.run_i3_command(&format!(
-            "move container to workspace {}",
-            self.i3_workspace_name()
+            "move container to workspace \"{}\"",
+            quote(&self.i3_workspace_name(&format))
         ))
     }
 
@@ -220,7 +329,6 @@ pub fn cmp_group_and_workspace(&self, other: &Self) -> std::cmp::Ordering {
         }
         self.group_workspace().cmp(&other.group_workspace())
     }
-
 }
 
 pub struct Workspace {
@@ -229,9 +337,12 @@ pub struct Workspace {
 }
 
 impl Workspace {
-    pub fn of_i3_workspace(workspace: i3ipc_jl::reply::Workspace) -> Self {
+    pub fn of_i3_workspace(
+        workspace: i3ipc_jl::reply::Workspace,
+        format: &WorkspaceNameFormat,
+    ) -> Self {
         Workspace {
-            workspace_with_group: WorkspaceID::of_i3_workspace(&workspace.name),
+            workspace_with_group: WorkspaceID::of_i3_workspace(&workspace.name, format),
             workspace,
         }
     }
@@ -242,20 +353,47 @@ pub fn of_i3_workspace_name(state: &crate::state::State, name: &str) -> Result<S
             .into_iter()
             .find(|w| w.name == name)
             .ok_or(anyhow::anyhow!("Workspace not found"))?;
-        Ok(Workspace::of_i3_workspace(workspace))
+        Ok(Workspace::of_i3_workspace(
+            workspace,
+            &state.workspace_name_format(),
+        ))
     }
 
     pub fn id(&self) -> &WorkspaceID {
         &self.workspace_with_group
     }
 
+    /// Actual name of the i3 workspace
+    pub fn name(&self) -> &str {
+        &self.workspace.name
+    }
+
     pub fn focus(&self, state: &crate::state::State) -> Result<()> {
-        self.workspace_with_group.focus(state)
+        state.run_i3_command(&format!("workspace \"{}\"", quote(self.name())))
+    }
+
+    /// Renames the workspace by its actual name, which may differ from the formatted one, e.g. for names written by
+    /// older versions
+    pub fn rename(&self, state: &crate::state::State, new_id: &WorkspaceID) -> Result<()> {
+        let format = state.workspace_name_format();
+        rename_i3_workspace(state, self.name(), &new_id.i3_workspace_name(&format))
+    }
+
+    /// Moves the focused container to the workspace by its actual name
+    pub fn move_container_to(&self, state: &crate::state::State) -> Result<()> {
+        state.run_i3_command(&format!(
+            "move container to workspace \"{}\"",
+            quote(self.name())
+        ))
     }
 
     pub fn list(state: &crate::state::State) -> Result<Vec<Self>> {
         let workspaces = get_i3_workspaces(state)?;
-        Ok(workspaces.into_iter().map(Self::of_i3_workspace).collect())
+        let format = state.workspace_name_format();
+        Ok(workspaces
+            .into_iter()
+            .map(|w| Self::of_i3_workspace(w, &format))
+            .collect())
     }
 
     pub fn get_focused(state: &crate::state::State) -> Result<Self> {
@@ -267,32 +405,246 @@ pub fn get_focused(state: &crate::state::State) -> Result<Self> {
     }
 }
 
-pub fn reassign_i3_ids(state: &crate::state::State) -> Result<()> {
-    let mut ids = Workspace::list(state)?
-        .into_iter()
-        .map(|w| w.id().to_owned())
+fn rename_i3_workspace_command(name: &str, new_name: &str) -> String {
+    format!(
+        "rename workspace \"{}\" to \"{}\"",
+        quote(name),
+        quote(new_name)
+    )
+}
+
+fn rename_i3_workspace(state: &crate::state::State, name: &str, new_name: &str) -> Result<()> {
+    state.run_i3_command(&rename_i3_workspace_command(name, new_name))
+}
+
+/// Orders renames so that no workspace gets a name which is still in use. If the renames form a cycle, one of the
+/// workspaces is renamed to a temporary name first. names are the names of all existing workspaces
+fn order_renames(
+    names: &[&str],
+    mut pending: Vec<(String, String)>,
+) -> Result<Vec<(String, String)>> {
+    let mut occupied = names
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<std::collections::HashSet<_>>();
+    let sources = pending
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<std::collections::HashSet<_>>();
+    let mut targets = std::collections::HashSet::new();
+    for (_, new_name) in &pending {
+        if !targets.insert(new_name.as_str())
+            || (occupied.contains(new_name) && !sources.contains(new_name.as_str()))
+        {
+            anyhow::bail!("Workspace name {:?} is already taken", new_name)
+        }
+    }
+
+    let mut ordered = Vec::new();
+    let mut temporary_index = 0;
+    while !pending.is_empty() {
+        match pending
+            .iter()
+            .position(|(_, new_name)| !occupied.contains(new_name))
+        {
+            Some(position) => {
+                let (name, new_name) = pending.remove(position);
+                occupied.remove(&name);
+                occupied.insert(new_name.clone());
+                ordered.push((name, new_name));
+            }
+            None => {
+                // Every target is the current name of another pending workspace
+                let temporary = loop {
+                    let temporary = format!("{TEMPORARY_NAME_PREFIX}{temporary_index}");
+                    temporary_index += 1;
+                    if !occupied.contains(&temporary) {
+                        break temporary;
+                    }
+                };
+                let name = std::mem::replace(&mut pending[0].0, temporary.clone());
+                occupied.remove(&name);
+                occupied.insert(temporary.clone());
+                ordered.push((name, temporary));
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+/// Fails naming the renames rejected by the window manager and the workspaces left with temporary names
+fn check_rename_outcomes(
+    renames: &[(String, String)],
+    outcomes: &[i3ipc_jl::reply::CommandOutcome],
+) -> Result<()> {
+    let mut failed = Vec::new();
+    let mut temporary = std::collections::BTreeSet::new();
+    for (index, (name, new_name)) in renames.iter().enumerate() {
+        match outcomes.get(index) {
+            Some(outcome) if outcome.success => {
+                temporary.remove(name);
+                if new_name.starts_with(TEMPORARY_NAME_PREFIX) {
+                    temporary.insert(new_name.clone());
+                }
+            }
+            outcome => failed.push(format!(
+                "{:?} -> {:?}: {}",
+                name,
+                new_name,
+                outcome
+                    .and_then(|v| v.error.as_deref())
+                    .unwrap_or("not run")
+            )),
+        }
+    }
+    if failed.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!("Failed to rename workspaces: {}", failed.join("; "));
+    if !temporary.is_empty() {
+        message.push_str(&format!(
+            ". Workspaces left with temporary names: {}",
+            temporary.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    Err(anyhow::anyhow!(message))
+}
+
+/// Fails if some workspaces have names of the current group, but the group is not known. Renaming them would move
+/// them to groups made of their numbers
+fn check_known_groups(state: &crate::state::State, workspaces: &[Workspace]) -> Result<()> {
+    let format = state.workspace_name_format();
+    let unknown = workspaces
+        .iter()
+        .map(|w| w.name())
+        .filter(|name| format.has_unknown_group(name))
         .collect::<Vec<_>>();
-    ids.sort_by(|a, b| a.cmp_group_and_workspace(b));
-    let mut i3_id = ids
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "Group of workspaces {} is unknown, refusing to rename them. Rename them by hand to the workspace name \
+             format, e.g. with i3-msg 'rename workspace \"12:3\" to \"12:group:3\"'",
+            unknown.join(", ")
+        )
+    }
+    Ok(())
+}
+
+/// Renumbers workspaces of groups to keep them sorted. Workspaces of the focused group get names of the current group
+/// name format, if it is configured
+pub fn reassign_i3_ids(state: &crate::state::State) -> Result<()> {
+    let mut workspaces = Workspace::list(state)?;
+    check_known_groups(state, &workspaces)?;
+    workspaces.sort_by(|a, b| a.id().cmp_group_and_workspace(b.id()));
+    let mut i3_id = workspaces
         .iter()
-        .filter(|id| id.is_just_i3_id())
-        .filter_map(|id| id.i3_id())
+        .filter(|w| w.id().is_just_i3_id())
+        .filter_map(|w| w.id().i3_id())
         .max()
         .unwrap_or(0)
         + 1;
-    for id in ids {
-        if id.is_just_i3_id() {
+    let focused_group = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .and_then(|w| w.id().group().cloned());
+    let format = state.workspace_name_format_for(focused_group.as_deref());
+    let mut renames = Vec::new();
+    for workspace in &workspaces {
+        if workspace.id().is_just_i3_id() {
             continue;
         }
-        slog_scope::info!("Renaming {:?}", id.i3_workspace_name());
-        let new_id = id.clone().with_i3_id(i3_id);
-        id.rename(state, &new_id)?;
-        i3_id += 1
+        let new_name = workspace
+            .id()
+            .clone()
+            .with_i3_id(i3_id)
+            .i3_workspace_name(&format);
+        i3_id += 1;
+        // Name formats without {id} don't change
+        if new_name != workspace.name() {
+            renames.push((workspace.name().to_owned(), new_name))
+        }
+    }
+
+    // All renames are planned from one snapshot of workspaces and sent as a single command, so the bar doesn't
+    // flicker
+    let names = workspaces.iter().map(|w| w.name()).collect::<Vec<_>>();
+    let renames = order_renames(&names, renames)?;
+    if !renames.is_empty() {
+        let command = renames
+            .iter()
+            .map(|(name, new_name)| {
+                slog_scope::info!("Renaming {:?} to {:?}", name, new_name);
+                rename_i3_workspace_command(name, new_name)
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        check_rename_outcomes(&renames, &state.run_i3_command_outcomes(&command)?)?;
+    }
+    state.set_labelled_group(focused_group.as_deref());
+    Ok(())
+}
+
+/// Renames workspaces after the focused group has changed, if workspaces of the current group have their own names
+fn update_labels(state: &crate::state::State) -> Result<()> {
+    if state.with_config(|config| config.current_group_name_format.is_some()) {
+        reassign_i3_ids(state)?;
     }
     Ok(())
 }
 
+fn focus_output(state: &crate::state::State, output: &str) -> Result<()> {
+    state.run_i3_command(&format!("focus output \"{}\"", quote(output)))
+}
+
+/// Shows the remembered workspaces of the group on outputs other than start_output, then returns focus to
+/// start_output. Returns true if some of the workspaces had to be created
+fn restore_output_workspaces(
+    state: &crate::state::State,
+    workspaces: &[Workspace],
+    group: Option<&str>,
+    start_output: &str,
+    target_workspace: i64,
+    output_workspaces: &std::collections::HashMap<String, i64>,
+) -> Result<bool> {
+    let active_outputs = workspaces
+        .iter()
+        .map(|w| w.workspace.output.as_str())
+        .collect::<std::collections::HashSet<_>>();
+    let mut restored = false;
+    let mut created = false;
+    for (output, number) in output_workspaces {
+        if output == start_output
+            || *number == target_workspace
+            || !active_outputs.contains(output.as_str())
+        {
+            continue;
+        }
+        let existing = workspaces.iter().find(|ws| {
+            ws.id().group_matches(group) && ws.id().effective_workspace_number() == Some(*number)
+        });
+        match existing {
+            Some(existing) => existing.focus(state)?,
+            None => {
+                focus_output(state, output)?;
+                match group {
+                    Some(group_name) => {
+                        WorkspaceID::GroupWithWorkspace(group_name.to_owned(), *number)
+                    }
+                    None => WorkspaceID::JustI3ID(*number),
+                }
+                .focus(state)?;
+                created = true;
+            }
+        }
+        restored = true;
+    }
+    if restored {
+        focus_output(state, start_output)?;
+    }
+    Ok(created)
+}
+
 pub fn focus_group(state: &crate::state::State, group: Option<&str>) -> Result<()> {
+    let format = state.workspace_name_format();
     slog_scope::debug!("focus_group called with group={:?}", group);
 
     // Get all workspaces in a single IPC call
@@ -306,29 +658,37 @@ pub fn focus_group(state: &crate::state::State, group: Option<&str>) -> Result<(
 
     slog_scope::debug!(
         "Current focused workspace: name={}, group={:?}, group_workspace={:?}",
-        current.id().i3_workspace_name(),
+        current.name(),
         current.id().group(),
         current.id().group_workspace()
     );
 
-    // Get target workspace number, saving current workspace state in the same operation
-    let target_workspace = if let Some(current_ws_num) = current.id().effective_workspace_number() {
-        slog_scope::debug!(
-            "Saving current workspace: group={:?}, ws_num={}",
-            current.id().group(),
-            current_ws_num
-        );
-        state
-            .update_and_get_last_workspace(
-                current.id().group().map(|s| s.as_str()),
-                current_ws_num,
-                group,
-            )
-            .unwrap_or(DEFAULT_WORKSPACE)
-    } else {
-        slog_scope::debug!("Current workspace has no effective number, not saving");
-        state.get_last_workspace(group).unwrap_or(DEFAULT_WORKSPACE)
-    };
+    // Remember the workspace of the current group and its workspaces shown on all outputs, the target group gets its
+    // ones restored
+    let current_group = current.id().group().map(|s| s.as_str());
+    let visible = workspaces
+        .iter()
+        .filter(|w| w.workspace.visible && w.id().group_matches(current_group))
+        .filter_map(|w| {
+            Some((
+                w.workspace.output.clone(),
+                w.id().effective_workspace_number()?,
+            ))
+        })
+        .collect();
+    let switch = state.switch_group(
+        current_group,
+        current.id().effective_workspace_number(),
+        &visible,
+        group,
+    );
+    let output_workspaces = switch.output_workspaces;
+    let start_output = &current.workspace.output;
+    let target_workspace = output_workspaces
+        .get(start_output)
+        .copied()
+        .or(switch.last_workspace)
+        .unwrap_or(DEFAULT_WORKSPACE);
 
     slog_scope::debug!(
         "Target workspace for group {:?}: {}",
@@ -336,11 +696,31 @@ pub fn focus_group(state: &crate::state::State, group: Option<&str>) -> Result<(
         target_workspace
     );
 
+    // Remember the focused window of the current group. The one of the target group gets focused if it is still there
+    mark_focused_window(state, current_group)?;
+
+    let created = restore_output_workspaces(
+        state,
+        &workspaces,
+        group,
+        start_output,
+        target_workspace,
+        &output_workspaces,
+    )?;
+    let finish = |state: &crate::state::State| {
+        focus_marked_window(state, &workspaces, group)?;
+        if created {
+            reassign_i3_ids(state)
+        } else {
+            update_labels(state)
+        }
+    };
+
     slog_scope::debug!("All workspaces:");
     for ws in &workspaces {
         slog_scope::debug!(
             "  - name={}, group={:?}, group_workspace={:?}",
-            ws.id().i3_workspace_name(),
+            ws.name(),
             ws.id().group(),
             ws.id().group_workspace()
         );
@@ -352,19 +732,18 @@ pub fn focus_group(state: &crate::state::State, group: Option<&str>) -> Result<(
             continue;
         }
         if workspace.id().effective_workspace_number() == Some(target_workspace) {
-            slog_scope::debug!("Found exact match: {}", workspace.id().i3_workspace_name());
-            return workspace.id().focus(state);
+            slog_scope::debug!("Found exact match: {}", workspace.name());
+            workspace.focus(state)?;
+            return finish(state);
         }
     }
 
     // If exact match not found, try any workspace in the group
     for workspace in &workspaces {
         if workspace.id().group_matches(group) {
-            slog_scope::debug!(
-                "Found workspace in group (not exact): {}",
-                workspace.id().i3_workspace_name()
-            );
-            return workspace.id().focus(state);
+            slog_scope::debug!("Found workspace in group (not exact): {}", workspace.name());
+            workspace.focus(state)?;
+            return finish(state);
         }
     }
 
@@ -375,29 +754,127 @@ pub fn focus_group(state: &crate::state::State, group: Option<&str>) -> Result<(
         }
         None => WorkspaceID::JustI3ID(target_workspace),
     };
-    slog_scope::debug!("Creating new workspace: {}", new_id.i3_workspace_name());
+    slog_scope::debug!(
+        "Creating new workspace: {}",
+        new_id.i3_workspace_name(&format)
+    );
     new_id.focus(state)?;
     reassign_i3_ids(state)
 }
 
+/// What to do with a workspace whose number already exists in the target group of a rename
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RenameConflict {
+    /// Don't rename anything
+    Fail,
+    /// Move windows into the existing workspace
+    Merge,
+    /// Give the workspace the next free number
+    Renumber,
+}
+
+/// Moves all windows of one workspace to another, both are given by their actual names
+fn move_workspace_windows(state: &crate::state::State, from: &str, to: &str) -> Result<()> {
+    state.run_i3_command(&format!(
+        "[workspace=\"^{}$\"] move container to workspace \"{}\"",
+        quote(&regex::escape(from)),
+        quote(to)
+    ))
+}
+
+/// Renames group to new_group. Clashing workspace numbers are detected before anything is renamed and resolved
+/// according to on_conflict. Returns the list of performed changes.
 pub fn rename_group(
     state: &crate::state::State,
     group: Option<&str>,
     new_group: Option<&str>,
-) -> Result<()> {
-    for workspace in Workspace::list(state)? {
-        if workspace.id().group().map(|v| v.as_str()) != group {
-            continue;
+    on_conflict: RenameConflict,
+) -> Result<Vec<String>> {
+    let format = state.workspace_name_format();
+    if group == new_group {
+        return Ok(Vec::new());
+    }
+    let workspaces = Workspace::list(state)?;
+    check_known_groups(state, &workspaces)?;
+    let (sources, targets): (Vec<_>, Vec<_>) = workspaces
+        .iter()
+        .filter(|ws| ws.id().group_matches(group) || ws.id().group_matches(new_group))
+        .partition(|ws| ws.id().group_matches(group));
+    let find_clash = |source: &Workspace| {
+        let number = source.id().effective_workspace_number()?;
+        targets
+            .iter()
+            .find(|target| target.id().effective_workspace_number() == Some(number))
+            .copied()
+    };
+
+    if on_conflict == RenameConflict::Fail {
+        let clashes = sources
+            .iter()
+            .filter_map(|source| find_clash(source))
+            .map(|target| target.name())
+            .collect::<Vec<_>>();
+        if !clashes.is_empty() {
+            anyhow::bail!(
+                "Workspaces already exist in the target group: {}. Use --on-conflict merge or renumber",
+                clashes.join(", ")
+            )
         }
-        let new_id = workspace.id().with_group(new_group);
-        workspace.id().rename(state, &new_id)?;
-        state.run_i3_command(&format!(
-            "rename workspace \"{}\" to \"{}\"",
-            workspace.id().i3_workspace_name(),
-            new_id.i3_workspace_name()
-        ))?;
     }
-    reassign_i3_ids(state)
+
+    let mut taken_numbers = sources
+        .iter()
+        .chain(targets.iter())
+        .filter_map(|ws| ws.id().effective_workspace_number())
+        .collect::<std::collections::HashSet<_>>();
+    let mut report = Vec::new();
+    for source in &sources {
+        let source_name = source.name();
+        match find_clash(source) {
+            Some(target) if on_conflict == RenameConflict::Merge => {
+                move_workspace_windows(state, source.name(), target.name())?;
+                if source.workspace.focused {
+                    target.focus(state)?;
+                }
+                report.push(format!(
+                    "Moved windows from {:?} to {:?}",
+                    source_name,
+                    target.name()
+                ));
+            }
+            Some(_) => {
+                let mut number = source
+                    .id()
+                    .effective_workspace_number()
+                    .unwrap_or(DEFAULT_WORKSPACE);
+                while taken_numbers.contains(&number) {
+                    number += 1
+                }
+                taken_numbers.insert(number);
+                let new_id = source
+                    .id()
+                    .with_group(new_group)
+                    .with_group_workspace(number);
+                source.rename(state, &new_id)?;
+                report.push(format!(
+                    "Renamed {:?} to {:?} (renumbered)",
+                    source_name,
+                    new_id.i3_workspace_name(&format)
+                ));
+            }
+            None => {
+                let new_id = source.id().with_group(new_group);
+                source.rename(state, &new_id)?;
+                report.push(format!(
+                    "Renamed {:?} to {:?}",
+                    source_name,
+                    new_id.i3_workspace_name(&format)
+                ));
+            }
+        }
+    }
+    reassign_i3_ids(state)?;
+    Ok(report)
 }
 
 pub fn move_window_to_group_workspace(
@@ -414,7 +891,7 @@ pub fn move_window_to_group_workspace(
             && ws.id().group_workspace() == Some(group_workspace)
     });
     if let Some(existing) = existing {
-        return existing.id().move_container_to(state);
+        return existing.move_container_to(state);
     }
     focused
         .id()
@@ -424,11 +901,18 @@ pub fn move_window_to_group_workspace(
     reassign_i3_ids(state)
 }
 
+/// Focuses the workspace by its i3 name
+pub fn focus_i3_workspace(state: &crate::state::State, name: &str) -> Result<()> {
+    Workspace::of_i3_workspace_name(state, name)?.focus(state)?;
+    update_labels(state)
+}
+
 pub fn move_window_to_workspace(state: &crate::state::State, id: &str) -> Result<()> {
-    let new_id = WorkspaceID::of_i3_workspace(id);
+    let format = state.workspace_name_format();
+    let new_id = WorkspaceID::of_i3_workspace(id, &format);
     state.run_i3_command(&format!(
-        "move container to workspace {}",
-        new_id.i3_workspace_name()
+        "move container to workspace \"{}\"",
+        quote(&new_id.i3_workspace_name(&format))
     ))
 }
 
@@ -442,6 +926,7 @@ pub fn focus_group_workspace(state: &crate::state::State, group_workspace: i64)
         ws.id().group() == focused.id().group()
             && ws.id().group_workspace() == Some(group_workspace)
     });
+    record_workspace_switch(state, focused, group_workspace);
     if let Some(existing) = existing {
         return existing.focus(state);
     }
@@ -452,3 +937,461 @@ pub fn focus_group_workspace(state: &crate::state::State, group_workspace: i64)
         .focus(state)?;
     reassign_i3_ids(state)
 }
+
+/// Saves the switch from the focused workspace to another workspace of its group in the workspace history
+fn record_workspace_switch(state: &crate::state::State, focused: &Workspace, group_workspace: i64) {
+    if let Some(current) = focused.id().effective_workspace_number() {
+        state.record_workspace_switch(
+            focused.id().group().map(|s| s.as_str()),
+            current,
+            group_workspace,
+        )
+    }
+}
+
+/// Focuses the most recently used workspace of the current group other than the focused one
+pub fn focus_previous_group_workspace(state: &crate::state::State) -> Result<()> {
+    let workspaces = Workspace::list(state)?;
+    let focused = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    let current = focused.id().effective_workspace_number();
+    let previous = state
+        .get_workspace_history(focused.id().group().map(|s| s.as_str()))
+        .into_iter()
+        .find(|v| Some(*v) != current);
+    match previous {
+        Some(previous) => focus_group_workspace(state, previous),
+        None => {
+            slog_scope::debug!("No previous workspace in the group");
+            Ok(())
+        }
+    }
+}
+
+/// Focuses the most recently used group other than the focused one
+pub fn focus_previous_group(state: &crate::state::State) -> Result<()> {
+    let workspaces = Workspace::list(state)?;
+    let focused = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    let current_group = focused.id().group().cloned();
+    let previous = state
+        .get_group_history()
+        .into_iter()
+        .find(|group| group != &current_group);
+    match previous {
+        Some(previous) => focus_group(state, previous.as_deref()),
+        None => {
+            slog_scope::debug!("No previous group");
+            Ok(())
+        }
+    }
+}
+
+/// Focuses the next or previous existing workspace of the current group, in the order of group workspace numbers
+pub fn focus_adjacent_group_workspace(
+    state: &crate::state::State,
+    direction: Direction,
+    wrap: bool,
+    skip_empty: bool,
+) -> Result<()> {
+    let workspaces = Workspace::list(state)?;
+    let focused = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    let non_empty = if skip_empty {
+        Some(get_non_empty_workspace_names(state)?)
+    } else {
+        None
+    };
+
+    let mut group_workspaces = workspaces
+        .iter()
+        .filter(|ws| ws.id().group() == focused.id().group())
+        .filter(|ws| {
+            ws.workspace.focused
+                || non_empty
+                    .as_ref()
+                    .map(|non_empty| non_empty.contains(&ws.workspace.name))
+                    .unwrap_or(true)
+        })
+        .collect::<Vec<_>>();
+    group_workspaces.sort_by_key(|ws| ws.id().effective_workspace_number());
+
+    let position = group_workspaces
+        .iter()
+        .position(|ws| ws.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    match direction.step(position, group_workspaces.len(), wrap) {
+        Some(target) if target != position => {
+            let target = group_workspaces[target];
+            if let Some(group_workspace) = target.id().effective_workspace_number() {
+                record_workspace_switch(state, focused, group_workspace);
+            }
+            target.focus(state)
+        }
+        _ => {
+            slog_scope::debug!("No {:?} workspace in the group", direction);
+            Ok(())
+        }
+    }
+}
+
+/// Focuses the next or previous group. The target group gets its last workspace restored, as with focus_group()
+pub fn focus_adjacent_group(
+    state: &crate::state::State,
+    direction: Direction,
+    order: GroupOrder,
+    wrap: bool,
+) -> Result<()> {
+    let workspaces = Workspace::list(state)?;
+    let focused = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    let current_group = focused.id().group().cloned();
+
+    let mut groups = workspaces
+        .iter()
+        .map(|ws| ws.id().group().cloned())
+        .collect::<Vec<_>>();
+    groups.sort();
+    groups.dedup();
+    if let GroupOrder::Recency = order {
+        let history = state.get_group_history();
+        // Stable sort: groups missing in the history stay in alphabetical order at the end
+        groups.sort_by_key(|group| {
+            history
+                .iter()
+                .position(|v| v == group)
+                .unwrap_or(usize::MAX)
+        });
+    }
+    slog_scope::debug!("Groups in {:?} order: {:?}", order, groups);
+
+    let position = groups
+        .iter()
+        .position(|group| group == &current_group)
+        .ok_or(anyhow::anyhow!("Current group not found"))?;
+    match direction.step(position, groups.len(), wrap) {
+        Some(target) if target != position => focus_group(state, groups[target].as_deref()),
+        _ => {
+            slog_scope::debug!("No {:?} group", direction);
+            Ok(())
+        }
+    }
+}
+
+/// Moves the focused window to a workspace of another group. Without group_workspace the last focused workspace of the
+/// target group is used. With follow, the target workspace gets focused.
+pub fn move_window_to_group(
+    state: &crate::state::State,
+    group: Option<&str>,
+    group_workspace: Option<i64>,
+    follow: bool,
+) -> Result<()> {
+    let format = state.workspace_name_format();
+    let group_workspace = group_workspace
+        .or_else(|| state.get_last_workspace(group))
+        .unwrap_or(DEFAULT_WORKSPACE);
+
+    let workspaces = Workspace::list(state)?;
+    let focused = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    let existing = workspaces.iter().find(|ws| {
+        ws.id().group_matches(group)
+            && ws.id().effective_workspace_number() == Some(group_workspace)
+    });
+
+    // An existing workspace is addressed by its actual name, which may differ from the formatted one
+    let target = match existing {
+        Some(existing) => {
+            existing.move_container_to(state)?;
+            existing.name().to_owned()
+        }
+        None => {
+            let target = match group {
+                Some(group_name) => {
+                    WorkspaceID::GroupWithWorkspace(group_name.to_owned(), group_workspace)
+                }
+                None => WorkspaceID::JustI3ID(group_workspace),
+            };
+            target.move_container_to(state)?;
+            slog_scope::debug!("Created workspace {}", target.i3_workspace_name(&format));
+            reassign_i3_ids(state)?;
+            // The new workspace got an i3 id, so its name has changed
+            Workspace::list(state)?
+                .into_iter()
+                .find(|ws| {
+                    ws.id().group_matches(group)
+                        && ws.id().effective_workspace_number() == Some(group_workspace)
+                })
+                .ok_or(anyhow::anyhow!("Target workspace not found"))?
+                .name()
+                .to_owned()
+        }
+    };
+
+    if !follow {
+        return Ok(());
+    }
+    state.switch_group(
+        focused.id().group().map(|s| s.as_str()),
+        focused.id().effective_workspace_number(),
+        &std::collections::HashMap::new(),
+        group,
+    );
+    state.run_i3_command(&format!("workspace \"{}\"", quote(&target)))?;
+    update_labels(state)
+}
+
+/// Moves the focused workspace to another group, keeping its number unless group_workspace is given. If the number is
+/// taken in the target group, either the next free number is used or, with swap, the two workspaces exchange places.
+pub fn move_workspace_to_group(
+    state: &crate::state::State,
+    group: Option<&str>,
+    group_workspace: Option<i64>,
+    swap: bool,
+) -> Result<()> {
+    let format = state.workspace_name_format();
+    let workspaces = Workspace::list(state)?;
+    check_known_groups(state, &workspaces)?;
+    let focused = workspaces
+        .iter()
+        .find(|w| w.workspace.focused)
+        .ok_or(anyhow::anyhow!("No focused workspace"))?;
+    let current_group = focused.id().group().map(|s| s.as_str());
+    let current_number = focused
+        .id()
+        .effective_workspace_number()
+        .unwrap_or(DEFAULT_WORKSPACE);
+    let mut number = group_workspace.unwrap_or(current_number);
+    if current_group == group && current_number == number {
+        return Ok(());
+    }
+
+    let is_taken = |number: i64| {
+        workspaces.iter().find(|ws| {
+            ws.workspace.name != focused.workspace.name
+                && ws.id().group_matches(group)
+                && ws.id().effective_workspace_number() == Some(number)
+        })
+    };
+
+    match is_taken(number) {
+        Some(occupant) if swap => {
+            // Go through a temporary name: in the default group the new names may be equal to the old ones
+            let temporary = WorkspaceID::JustGroup(format!("i3im-swap-{}", focused.name()));
+            focused.rename(state, &temporary)?;
+            let occupant_new = occupant
+                .id()
+                .with_group(current_group)
+                .with_group_workspace(current_number);
+            occupant.rename(state, &occupant_new)?;
+            slog_scope::info!(
+                "Workspace {:?} swapped to {:?}",
+                occupant.name(),
+                occupant_new.i3_workspace_name(&format)
+            );
+            let new_id = focused.id().with_group(group).with_group_workspace(number);
+            temporary.rename(state, &new_id)?;
+            slog_scope::info!(
+                "Workspace {:?} moved to {:?}",
+                focused.name(),
+                new_id.i3_workspace_name(&format)
+            );
+        }
+        occupant => {
+            if occupant.is_some() {
+                while is_taken(number).is_some() {
+                    number += 1
+                }
+                slog_scope::info!("Workspace number is taken, using {number}");
+            }
+            let new_id = focused.id().with_group(group).with_group_workspace(number);
+            focused.rename(state, &new_id)?;
+            slog_scope::info!(
+                "Workspace {:?} moved to {:?}",
+                focused.name(),
+                new_id.i3_workspace_name(&format)
+            );
+        }
+    }
+
+    reassign_i3_ids(state)
+}
+
+/// Kills all windows of the group or, with move_to, moves them to the workspaces with the same numbers in another
+/// group. Then the group is removed from the history. With dry_run nothing is changed. Returns the list of performed
+/// (or planned) changes.
+pub fn close_group(
+    state: &crate::state::State,
+    group: &str,
+    move_to: Option<Option<&str>>,
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    let format = state.workspace_name_format();
+    if move_to == Some(Some(group)) {
+        anyhow::bail!("Can't move windows of the group to the same group")
+    }
+    let workspaces = Workspace::list(state)?;
+    let window_names = get_workspace_window_names(state)?;
+    let prefix = if dry_run { "Would " } else { "" };
+    let mut report = Vec::new();
+    let mut created = false;
+    let mut focused_group = false;
+    for workspace in workspaces
+        .iter()
+        .filter(|ws| ws.id().group_matches(Some(group)))
+    {
+        let name = workspace.name();
+        focused_group |= workspace.workspace.focused;
+        let windows = window_names.get(name).cloned().unwrap_or_default();
+        if windows.is_empty() {
+            continue;
+        }
+        match move_to {
+            Some(target_group) => {
+                let number = workspace
+                    .id()
+                    .effective_workspace_number()
+                    .unwrap_or(DEFAULT_WORKSPACE);
+                let existing = workspaces.iter().find(|ws| {
+                    ws.id().group_matches(target_group)
+                        && ws.id().effective_workspace_number() == Some(number)
+                });
+                // An existing workspace is addressed by its actual name, which may differ from the formatted one
+                let target = match (existing, target_group) {
+                    (Some(existing), _) => existing.name().to_owned(),
+                    (None, Some(target_group)) => {
+                        WorkspaceID::GroupWithWorkspace(target_group.to_owned(), number)
+                            .i3_workspace_name(&format)
+                    }
+                    (None, None) => WorkspaceID::JustI3ID(number).i3_workspace_name(&format),
+                };
+                created |= existing.is_none();
+                for window in &windows {
+                    report.push(format!(
+                        "{prefix}move {window:?} from {name:?} to {target:?}"
+                    ));
+                }
+                if !dry_run {
+                    move_workspace_windows(state, workspace.name(), &target)?;
+                }
+            }
+            None => {
+                for window in &windows {
+                    report.push(format!("{prefix}kill {window:?} on {name:?}"));
+                }
+                if !dry_run {
+                    state.run_i3_command(&format!(
+                        "[workspace=\"^{}$\"] kill",
+                        quote(&regex::escape(name))
+                    ))?;
+                }
+            }
+        }
+    }
+    if dry_run {
+        return Ok(report);
+    }
+
+    if created {
+        reassign_i3_ids(state)?;
+    }
+    if focused_group {
+        focus_group(state, move_to.flatten())?;
+    }
+    state.forget_group(group);
+    report.push(format!("Removed group {group:?} from the history"));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use i3ipc_jl::reply::CommandOutcome;
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, new_name)| (name.to_string(), new_name.to_string()))
+            .collect()
+    }
+
+    /// Applies the renames one by one, failing if a name is still in use
+    fn apply(names: &[&str], renames: &[(String, String)]) -> Vec<String> {
+        let mut names = names.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        for (name, new_name) in renames {
+            assert!(!names.contains(new_name), "{new_name} is in use");
+            let position = names.iter().position(|v| v == name).unwrap();
+            names[position] = new_name.clone();
+        }
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn renames_are_ordered_without_temporary_names() {
+        let names = ["1:a:1", "2:a:2", "3:b:1"];
+        let ordered = order_renames(
+            &names,
+            renames(&[("1:a:1", "2:a:2"), ("2:a:2", "3:b:1"), ("3:b:1", "4:b:1")]),
+        )
+        .unwrap();
+        assert_eq!(
+            ordered,
+            renames(&[("3:b:1", "4:b:1"), ("2:a:2", "3:b:1"), ("1:a:1", "2:a:2")])
+        );
+        assert_eq!(apply(&names, &ordered), ["2:a:2", "3:b:1", "4:b:1"]);
+    }
+
+    #[test]
+    fn cycles_use_temporary_names() {
+        let names = ["1:a:1", "2:b:1"];
+        let ordered =
+            order_renames(&names, renames(&[("1:a:1", "2:b:1"), ("2:b:1", "1:a:1")])).unwrap();
+        assert_eq!(ordered.len(), 3);
+        assert_eq!(apply(&names, &ordered), ["1:a:1", "2:b:1"]);
+    }
+
+    #[test]
+    fn taken_names_are_rejected() {
+        let names = ["1:a:1", "5"];
+        assert!(order_renames(&names, renames(&[("1:a:1", "5")])).is_err());
+        assert!(order_renames(&names, renames(&[("1:a:1", "2:a:1"), ("5", "2:a:1")])).is_err());
+    }
+
+    #[test]
+    fn failed_renames_are_reported() {
+        let ordered = renames(&[("1", "i3im-tmp-0"), ("2", "1"), ("i3im-tmp-0", "2")]);
+        let outcome = |error: Option<&str>| CommandOutcome {
+            success: error.is_none(),
+            error: error.map(|v| v.to_owned()),
+        };
+        assert!(
+            check_rename_outcomes(&ordered, &[outcome(None), outcome(None), outcome(None)]).is_ok()
+        );
+        let error = check_rename_outcomes(&ordered, &[outcome(None), outcome(Some("taken"))])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            r#"Failed to rename workspaces: "2" -> "1": taken; "i3im-tmp-0" -> "2": not run. Workspaces left with temporary names: i3im-tmp-0"#
+        );
+    }
+
+    #[test]
+    fn rename_command_escapes_names() {
+        assert_eq!(
+            rename_i3_workspace_command("say \"hi\"", "a\\b"),
+            r#"rename workspace "say \"hi\"" to "a\\b""#
+        );
+    }
+}
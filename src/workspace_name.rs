@@ -0,0 +1,647 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use structdoc::StructDoc;
+
+use crate::workspace_group::WorkspaceID;
+
+const DEFAULT_FORMAT: &str = "{id}:{group}:{n}";
+
+/// Characters of group names which are always escaped: the escape character itself and characters with a special
+/// meaning in quoted strings of i3 commands
+const ALWAYS_ESCAPED: [char; 3] = ['%', '"', '\\'];
+
+/// Field of a workspace name
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// i3 workspace number, used by i3 for sorting
+    Id,
+    Group,
+    /// Workspace number inside the group
+    Number,
+}
+
+impl Field {
+    fn placeholder(&self) -> &'static str {
+        match self {
+            Field::Id => "{id}",
+            Field::Group => "{group}",
+            Field::Number => "{n}",
+        }
+    }
+
+    fn regex(&self) -> &'static str {
+        match self {
+            Field::Id | Field::Number => r"(\d+)",
+            Field::Group => "(.+)",
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// Workspace name layout with some of the fields of the format
+#[derive(Clone)]
+struct Template {
+    parts: Vec<Part>,
+    regex: regex::Regex,
+}
+
+impl Template {
+    /// Builds the template from parts of the format, dropping the given fields together with their separators
+    fn new(parts: &[Part], skip: &[Field]) -> Self {
+        let mut result: Vec<Part> = Vec::new();
+        let mut skip_separator = false;
+        for part in parts {
+            match part {
+                Part::Field(field) if skip.contains(field) => {
+                    // The first field takes the following separator with it, others take the preceding one
+                    if result.iter().any(|part| matches!(part, Part::Field(_))) {
+                        result.pop();
+                    } else {
+                        skip_separator = true;
+                    }
+                }
+                Part::Literal(_) if skip_separator => skip_separator = false,
+                _ => result.push(part.clone()),
+            }
+        }
+
+        let regex = result
+            .iter()
+            .map(|part| match part {
+                Part::Literal(literal) => regex::escape(literal),
+                Part::Field(field) => field.regex().to_owned(),
+            })
+            .collect::<String>();
+        let regex = regex::Regex::new(&format!("^{regex}$")).expect("Invalid workspace name regex");
+        Self {
+            parts: result,
+            regex,
+        }
+    }
+
+    fn fields(&self) -> impl Iterator<Item = Field> + '_ {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Field(field) => Some(*field),
+            Part::Literal(_) => None,
+        })
+    }
+
+    fn format(&self, i3_id: Option<i64>, group: &str, group_workspace: Option<i64>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(literal) => literal.clone(),
+                Part::Field(Field::Id) => i3_id.map(|v| v.to_string()).unwrap_or_default(),
+                Part::Field(Field::Group) => group.to_owned(),
+                Part::Field(Field::Number) => {
+                    group_workspace.map(|v| v.to_string()).unwrap_or_default()
+                }
+            })
+            .collect()
+    }
+
+    /// Returns (i3 id, group, group workspace) if the name matches the template
+    fn parse(&self, name: &str) -> Option<(Option<i64>, String, Option<i64>)> {
+        let captures = self.regex.captures(name)?;
+        let mut i3_id = None;
+        let mut group = String::new();
+        let mut group_workspace = None;
+        for (field, capture) in self.fields().zip(captures.iter().skip(1)) {
+            let value = capture?.as_str();
+            match field {
+                Field::Id => i3_id = Some(value.parse().ok()?),
+                Field::Group => group = value.to_owned(),
+                Field::Number => group_workspace = Some(value.parse().ok()?),
+            }
+        }
+        Some((i3_id, group, group_workspace))
+    }
+}
+
+/// Layout of i3 workspace names, e.g. `{id}:{group}:{n}`. Workspaces of the default group are named by their number
+/// only
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct WorkspaceNameFormat {
+    format: String,
+    has_id: bool,
+    /// Characters of group names which must be escaped
+    escaped: Vec<char>,
+    full: Template,
+    id_with_group: Template,
+    group_with_workspace: Template,
+    just_group: Template,
+    /// Template for workspaces of the current group and the name of the group
+    current_group: Option<(Template, String)>,
+    /// Template for workspaces of the current group without the group field, set when the current group is unknown.
+    /// Names matching it can't be attributed to any group
+    unknown_group: Option<Template>,
+}
+
+impl WorkspaceNameFormat {
+    fn parse_format(format: &str) -> Result<Vec<Part>> {
+        let placeholder = regex::Regex::new(r"\{[^{}]*\}").unwrap();
+        let mut parts = Vec::new();
+        let mut position = 0;
+        for found in placeholder.find_iter(format) {
+            if found.start() > position {
+                parts.push(Part::Literal(format[position..found.start()].to_owned()))
+            }
+            let field = [Field::Id, Field::Group, Field::Number]
+                .into_iter()
+                .find(|field| field.placeholder() == found.as_str())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown placeholder {}, expected {{id}}, {{group}} or {{n}}",
+                        found.as_str()
+                    )
+                })?;
+            parts.push(Part::Field(field));
+            position = found.end();
+        }
+        if position < format.len() {
+            parts.push(Part::Literal(format[position..].to_owned()))
+        }
+        Ok(parts)
+    }
+
+    /// Checks the layout. The group field is optional for names of the current group, the id field is optional for
+    /// other names
+    fn validate_parts(parts: &[Part], current_group: bool) -> Result<()> {
+        let count = |field: Field| {
+            parts
+                .iter()
+                .filter(|part| matches!(part, Part::Field(f) if *f == field))
+                .count()
+        };
+        if count(Field::Number) != 1 {
+            anyhow::bail!("{{n}} must be used exactly once")
+        }
+        match count(Field::Group) {
+            0 if current_group => (),
+            1 => (),
+            _ if current_group => anyhow::bail!("{{group}} can be used only once"),
+            _ => anyhow::bail!("{{group}} must be used exactly once"),
+        }
+        match count(Field::Id) {
+            0 if current_group => anyhow::bail!("{{id}} is required"),
+            0 => (),
+            1 if matches!(parts.first(), Some(Part::Field(Field::Id))) => (),
+            1 => anyhow::bail!(
+                "{{id}} must be at the beginning: i3 takes the workspace number from the start of the name"
+            ),
+            _ => anyhow::bail!("{{id}} can be used only once"),
+        }
+        for pair in parts.windows(2) {
+            if let [Part::Field(_), Part::Field(_)] = pair {
+                anyhow::bail!("Fields must be divided by a separator")
+            }
+        }
+        for part in parts {
+            if let Part::Literal(literal) = part {
+                if literal.contains(|c: char| c.is_ascii_digit() || c == '{' || c == '}') {
+                    anyhow::bail!("Separator {literal:?} must not contain digits or braces")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn literal_chars(parts: &[Part]) -> impl Iterator<Item = char> + '_ {
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Literal(literal) => Some(literal.chars()),
+                Part::Field(_) => None,
+            })
+            .flatten()
+    }
+
+    pub fn new(format: &str) -> Result<Self> {
+        let parts = Self::parse_format(format)?;
+        Self::validate_parts(&parts, false)
+            .map_err(|e| anyhow::anyhow!("Invalid workspace name format {format:?}: {e}"))?;
+        let mut escaped = ALWAYS_ESCAPED.to_vec();
+        escaped.extend(Self::literal_chars(&parts));
+        escaped.sort_unstable();
+        escaped.dedup();
+        Ok(Self {
+            format: format.to_owned(),
+            has_id: parts
+                .iter()
+                .any(|part| matches!(part, Part::Field(Field::Id))),
+            escaped,
+            full: Template::new(&parts, &[]),
+            id_with_group: Template::new(&parts, &[Field::Number]),
+            group_with_workspace: Template::new(&parts, &[Field::Id]),
+            just_group: Template::new(&parts, &[Field::Id, Field::Number]),
+            current_group: None,
+            unknown_group: None,
+        })
+    }
+
+    /// Returns the format which names workspaces of the given group with current_group_format. Separators of
+    /// current_group_format are escaped in all group names, so the names don't depend on which group is current
+    pub fn with_current_group(
+        &self,
+        current_group_format: Option<&CurrentGroupNameFormat>,
+        group: Option<&str>,
+    ) -> Self {
+        let mut result = self.clone();
+        if let Some(current_group_format) = current_group_format {
+            result
+                .escaped
+                .extend(Self::literal_chars(&current_group_format.parts));
+            result.escaped.sort_unstable();
+            result.escaped.dedup();
+            result.current_group =
+                group.map(|group| (current_group_format.template.clone(), group.to_owned()));
+            let template = &current_group_format.template;
+            if group.is_none() && !template.fields().any(|field| field == Field::Group) {
+                result.unknown_group = Some(template.clone());
+            }
+        }
+        result
+    }
+
+    /// Escapes characters of the group name which could be taken for a part of the name layout as %XX. The first
+    /// digit of a purely numeric name is escaped too, otherwise the name could be taken for a workspace number
+    fn encode_group(&self, group: &str) -> String {
+        let numeric = !group.is_empty() && group.chars().all(|c| c.is_ascii_digit());
+        let mut result = String::with_capacity(group.len());
+        for (index, c) in group.chars().enumerate() {
+            if (numeric && index == 0) || self.escaped.contains(&c) {
+                let mut buffer = [0; 4];
+                for byte in c.encode_utf8(&mut buffer).bytes() {
+                    result.push_str(&format!("%{byte:02X}"))
+                }
+            } else {
+                result.push(c)
+            }
+        }
+        result
+    }
+
+    /// Reverts encode_group. Invalid escape sequences, e.g. in workspaces named by hand, are kept as is
+    fn decode_group(group: &str) -> String {
+        let bytes = group.as_bytes();
+        let mut result = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+        while index < bytes.len() {
+            let escaped = match bytes.get(index..index + 3) {
+                Some([b'%', high, low]) if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                    std::str::from_utf8(&[*high, *low])
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                }
+                _ => None,
+            };
+            match escaped {
+                Some(byte) => {
+                    result.push(byte);
+                    index += 3
+                }
+                None => {
+                    result.push(bytes[index]);
+                    index += 1
+                }
+            }
+        }
+        String::from_utf8(result).unwrap_or_else(|_| group.to_owned())
+    }
+
+    pub fn format(&self, id: &WorkspaceID) -> String {
+        match id {
+            WorkspaceID::JustI3ID(i3_id) => format!("{i3_id}"),
+            WorkspaceID::Full(i3_id, group_name, group_workspace) if matches!(&self.current_group, Some((_, group)) if group == group_name) =>
+            {
+                let (template, _) = self.current_group.as_ref().unwrap();
+                template.format(
+                    Some(*i3_id),
+                    &self.encode_group(group_name),
+                    Some(*group_workspace),
+                )
+            }
+            WorkspaceID::I3IDWithGroup(i3_id, group_name) if self.has_id => self
+                .id_with_group
+                .format(Some(*i3_id), &self.encode_group(group_name), None),
+            WorkspaceID::I3IDWithGroup(_, group_name) | WorkspaceID::JustGroup(group_name) => self
+                .just_group
+                .format(None, &self.encode_group(group_name), None),
+            WorkspaceID::GroupWithWorkspace(group_name, group_workspace) => self
+                .group_with_workspace
+                .format(None, &self.encode_group(group_name), Some(*group_workspace)),
+            WorkspaceID::Full(i3_id, group_name, group_workspace) => self.full.format(
+                Some(*i3_id),
+                &self.encode_group(group_name),
+                Some(*group_workspace),
+            ),
+        }
+    }
+
+    pub fn parse(&self, name: &str) -> WorkspaceID {
+        if let Ok(i3_id) = name.parse::<i64>() {
+            return WorkspaceID::JustI3ID(i3_id);
+        }
+        if let Some((template, group)) = &self.current_group {
+            // The group field is optional in the template, but if it is there, it must be the current group
+            match template.parse(name) {
+                Some((Some(i3_id), parsed_group, Some(group_workspace)))
+                    if parsed_group.is_empty() || Self::decode_group(&parsed_group) == *group =>
+                {
+                    return WorkspaceID::Full(i3_id, group.clone(), group_workspace)
+                }
+                _ => (),
+            }
+        }
+        let templates = [&self.full, &self.id_with_group, &self.group_with_workspace];
+        let parsed = templates
+            .into_iter()
+            .find_map(|template| template.parse(name));
+        match parsed {
+            Some((Some(i3_id), group_name, Some(group_workspace))) => {
+                WorkspaceID::Full(i3_id, Self::decode_group(&group_name), group_workspace)
+            }
+            Some((Some(i3_id), group_name, None)) => {
+                WorkspaceID::I3IDWithGroup(i3_id, Self::decode_group(&group_name))
+            }
+            Some((None, group_name, Some(group_workspace))) => {
+                WorkspaceID::GroupWithWorkspace(Self::decode_group(&group_name), group_workspace)
+            }
+            _ => WorkspaceID::JustGroup(Self::decode_group(
+                &self
+                    .just_group
+                    .parse(name)
+                    .map(|(_, group_name, _)| group_name)
+                    .unwrap_or_else(|| name.to_owned()),
+            )),
+        }
+    }
+
+    /// Returns true if the name has the layout of the current group names, but the current group is unknown, e.g. because
+    /// the state file was lost. Such a name would be taken for a name of another group
+    pub fn has_unknown_group(&self, name: &str) -> bool {
+        self.unknown_group
+            .as_ref()
+            .map(|template| template.parse(name).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Checks that the group name can be used in workspace names
+    pub fn validate_group_name(&self, group: &str) -> Result<()> {
+        if group.chars().any(char::is_control) {
+            anyhow::bail!("Group name {group:?} contains control characters")
+        }
+        let id = WorkspaceID::Full(1, group.to_owned(), 1);
+        if self.parse(&self.format(&id)) != id {
+            anyhow::bail!(
+                "Group name {group:?} can't be used with workspace name format {:?}",
+                self.format
+            )
+        }
+        Ok(())
+    }
+
+    /// Checks that names of all kinds of workspaces are parsed back to the same workspaces
+    pub fn validate_round_trip(&self) -> Result<()> {
+        let samples = [
+            WorkspaceID::JustI3ID(3),
+            WorkspaceID::I3IDWithGroup(3, "project".to_owned()),
+            WorkspaceID::JustGroup("project".to_owned()),
+            WorkspaceID::GroupWithWorkspace("project".to_owned(), 2),
+            WorkspaceID::Full(3, "project".to_owned(), 2),
+        ];
+        for sample in samples {
+            let name = self.format(&sample);
+            let parsed = self.parse(&name);
+            if parsed.group() != sample.group()
+                || parsed.group_workspace() != sample.group_workspace()
+                || self.format(&parsed) != name
+            {
+                anyhow::bail!(
+                    "Workspace name format {:?} is ambiguous: {:?} of {:?} is parsed as {:?}",
+                    self.format,
+                    name,
+                    sample,
+                    parsed
+                )
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Layout of names of workspaces of the focused group, e.g. `{id}:{n}`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CurrentGroupNameFormat {
+    format: String,
+    parts: Vec<Part>,
+    template: Template,
+}
+
+impl CurrentGroupNameFormat {
+    pub fn new(format: &str) -> Result<Self> {
+        let parts = WorkspaceNameFormat::parse_format(format)?;
+        WorkspaceNameFormat::validate_parts(&parts, true).map_err(|e| {
+            anyhow::anyhow!("Invalid current group workspace name format {format:?}: {e}")
+        })?;
+        Ok(Self {
+            format: format.to_owned(),
+            template: Template::new(&parts, &[]),
+            parts,
+        })
+    }
+
+    /// Checks that names of the current group and other names can't be confused
+    pub fn validate_round_trip(&self, workspace_name_format: &WorkspaceNameFormat) -> Result<()> {
+        let format = workspace_name_format.with_current_group(Some(self), Some("current"));
+        format.validate_round_trip()?;
+        let current = WorkspaceID::Full(3, "current".to_owned(), 2);
+        let name = format.format(&current);
+        if format.parse(&name) != current {
+            anyhow::bail!(
+                "Current group workspace name format {:?} doesn't round-trip: {:?} is parsed as {:?}",
+                self.format,
+                name,
+                format.parse(&name)
+            )
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for CurrentGroupNameFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(format: String) -> Result<Self> {
+        Self::new(&format)
+    }
+}
+
+impl From<CurrentGroupNameFormat> for String {
+    fn from(format: CurrentGroupNameFormat) -> Self {
+        format.format
+    }
+}
+
+impl StructDoc for CurrentGroupNameFormat {
+    fn document() -> structdoc::Documentation {
+        structdoc::Documentation::leaf(
+            "Workspace name format with {id} (required, at the beginning), {n} and optional {group} placeholders",
+        )
+    }
+}
+
+impl Default for WorkspaceNameFormat {
+    fn default() -> Self {
+        Self::new(DEFAULT_FORMAT).unwrap()
+    }
+}
+
+impl TryFrom<String> for WorkspaceNameFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(format: String) -> Result<Self> {
+        Self::new(&format)
+    }
+}
+
+impl From<WorkspaceNameFormat> for String {
+    fn from(format: WorkspaceNameFormat) -> Self {
+        format.format
+    }
+}
+
+impl StructDoc for WorkspaceNameFormat {
+    fn document() -> structdoc::Documentation {
+        structdoc::Documentation::leaf(
+            "Workspace name format with {id} (i3 number, optional, only at the beginning), {group} and {n} placeholders",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const FORMATS: [&str; 4] = [
+        DEFAULT_FORMAT,
+        "{group}/{n}",
+        "{id}:{group}·{n}",
+        "[{group}] {n}",
+    ];
+
+    fn workspace_id() -> impl Strategy<Value = WorkspaceID> {
+        let group = "\\PC+";
+        let number = 0..100_000i64;
+        prop_oneof![
+            number.clone().prop_map(WorkspaceID::JustI3ID),
+            (number.clone(), group)
+                .prop_map(|(i3_id, group)| WorkspaceID::I3IDWithGroup(i3_id, group)),
+            group.prop_map(WorkspaceID::JustGroup),
+            (group, number.clone())
+                .prop_map(|(group, n)| WorkspaceID::GroupWithWorkspace(group, n)),
+            (number.clone(), group, number)
+                .prop_map(|(i3_id, group, n)| WorkspaceID::Full(i3_id, group, n)),
+        ]
+    }
+
+    /// Formats without {id} don't keep the i3 id
+    fn expected(format: &WorkspaceNameFormat, id: WorkspaceID) -> WorkspaceID {
+        match id {
+            WorkspaceID::I3IDWithGroup(_, group) if !format.has_id => WorkspaceID::JustGroup(group),
+            WorkspaceID::Full(_, group, n) if !format.has_id => {
+                WorkspaceID::GroupWithWorkspace(group, n)
+            }
+            id => id,
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn parse_reverts_format(id in workspace_id()) {
+            for format in FORMATS {
+                let format = WorkspaceNameFormat::new(format).unwrap();
+                let name = format.format(&id);
+                prop_assert_eq!(format.parse(&name), expected(&format, id.clone()), "name {:?}", name);
+            }
+        }
+
+        #[test]
+        fn parse_reverts_format_with_current_group(id in workspace_id(), current in "\\PC+") {
+            let current_group_format = CurrentGroupNameFormat::new("{id}:{n}").unwrap();
+            let format = WorkspaceNameFormat::default()
+                .with_current_group(Some(&current_group_format), Some(&current));
+            let name = format.format(&id);
+            prop_assert_eq!(format.parse(&name), id, "name {:?}", name);
+        }
+
+        #[test]
+        fn format_reverts_parse(name in "\\PC*") {
+            let format = WorkspaceNameFormat::default();
+            let id = format.parse(&name);
+            prop_assert_eq!(format.parse(&format.format(&id)), id);
+        }
+    }
+
+    #[test]
+    fn formats_are_valid() {
+        for format in FORMATS {
+            WorkspaceNameFormat::new(format)
+                .unwrap()
+                .validate_round_trip()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn current_group_has_short_names() {
+        let current_group_format = CurrentGroupNameFormat::new("{id}:{n}").unwrap();
+        current_group_format
+            .validate_round_trip(&WorkspaceNameFormat::default())
+            .unwrap();
+        let format = WorkspaceNameFormat::default()
+            .with_current_group(Some(&current_group_format), Some("web"));
+        let current = WorkspaceID::Full(12, "web".to_owned(), 3);
+        assert_eq!(format.format(&current), "12:3");
+        assert_eq!(format.parse("12:3"), current);
+        let other = WorkspaceID::Full(13, "mail".to_owned(), 1);
+        assert_eq!(format.format(&other), "13:mail:1");
+        assert_eq!(format.parse("13:mail:1"), other);
+    }
+
+    #[test]
+    fn short_names_without_current_group_are_detected() {
+        let current_group_format = CurrentGroupNameFormat::new("{id}:{n}").unwrap();
+        let unknown =
+            WorkspaceNameFormat::default().with_current_group(Some(&current_group_format), None);
+        assert!(unknown.has_unknown_group("12:3"));
+        // A numeric group is escaped, so a name of another group never looks like a short name
+        assert!(!unknown
+            .has_unknown_group(&unknown.format(&WorkspaceID::I3IDWithGroup(12, "3".to_owned()))));
+        assert!(!unknown.has_unknown_group("12:web:3"));
+        assert!(!unknown.has_unknown_group("12"));
+        let known = WorkspaceNameFormat::default()
+            .with_current_group(Some(&current_group_format), Some("web"));
+        assert!(!known.has_unknown_group("12:3"));
+    }
+
+    #[test]
+    fn ambiguous_group_names_are_escaped() {
+        let format = WorkspaceNameFormat::default();
+        let numeric = WorkspaceID::GroupWithWorkspace("2024".to_owned(), 1);
+        assert_eq!(format.format(&numeric), "%32024:1");
+        let with_separator = WorkspaceID::Full(3, "foo:bar".to_owned(), 1);
+        assert_eq!(format.format(&with_separator), "3:foo%3Abar:1");
+        assert!(format.validate_group_name("2024").is_ok());
+        assert!(format.validate_group_name("foo:bar").is_ok());
+        assert!(format.validate_group_name("foo\nbar").is_err());
+    }
+}